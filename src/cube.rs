@@ -1,21 +1,55 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
 
 use lazy_static::lazy_static;
-use ndarray::{Array1, Array2};
 
-use crate::search::IDAStarNode;
+use crate::cubie::CubieCube;
+use crate::moves::{Algorithm, Move};
+use crate::pattern::PatternSpec;
+use crate::transformations;
+use crate::transformations::{Permutation, MAX_FACELETS};
 
 lazy_static! {
-    static ref CORNER_PERMUTATIONS: HashMap<String, usize> = all_corner_permutations();
-    static ref EDGES1_PERMUTATIONS: HashMap<String, usize> = all_edges1_permutations();
-    static ref EDGES2_PERMUTATIONS: HashMap<String, usize> = all_edges2_permutations();
-    static ref EDGES1_MASK: HashSet<Edge> = edges1_mask();
-    static ref EDGES2_MASK: HashSet<Edge> = edges2_mask();
+    static ref CUBE2: HashMap<Move, Permutation> = transformations::cube2();
+    static ref CUBE3: HashMap<Move, Permutation> = transformations::cube3();
 }
 
+// facelets of each corner position, listed clockwise starting from the U or D facelet
+pub(crate) const CORNER_FACELETS: [[usize; 3]; 8] = [
+    [0, 9, 38],
+    [2, 36, 29],
+    [6, 18, 11],
+    [8, 27, 20],
+    [51, 44, 15],
+    [53, 35, 42],
+    [45, 17, 24],
+    [47, 26, 33],
+];
+
+// facelets of each edge position, starting from the U or D facelet (F or B in the middle slice)
+pub(crate) const EDGE_FACELETS: [[usize; 2]; 12] = [
+    [1, 37],
+    [3, 10],
+    [5, 28],
+    [7, 19],
+    [41, 12],
+    [39, 32],
+    [21, 14],
+    [23, 30],
+    [52, 43],
+    [48, 16],
+    [50, 34],
+    [46, 25],
+];
+
+// faces in the order of the URFDLB facelet format, as indices into `data`
+const FACELET_ORDER: [usize; 6] = [0, 3, 2, 5, 1, 4];
+
+// the letter of each face, in the order faces are stored in `data`
+const FACE_LETTERS: [char; 6] = ['U', 'L', 'F', 'R', 'B', 'D'];
+
 #[derive(Debug)]
 enum Corner {
     BlueOrangeWhite,
@@ -29,7 +63,6 @@ enum Corner {
 }
 
 impl Corner {
-    // fix this with unreachable!
     fn from(v: [u8; 3]) -> Option<Corner> {
         match v {
             [0, 1, 4] => Some(Corner::BlueOrangeWhite),
@@ -58,60 +91,7 @@ impl Corner {
     }
 }
 
-fn all_corner_permutations() -> HashMap<String, usize> {
-    let path = Path::new("tables/corner_permutations.json");
-    let file = File::open(&path).unwrap();
-
-    let perms: HashMap<String, usize> = serde_json::from_reader(&file).unwrap();
-
-    perms
-}
-
-fn all_edges1_permutations() -> HashMap<String, usize> {
-    let path = Path::new("tables/edges1_permutations.json");
-    let file = File::open(&path).unwrap();
-
-    let perms: HashMap<String, usize> = serde_json::from_reader(&file).unwrap();
-
-    perms
-}
-
-fn all_edges2_permutations() -> HashMap<String, usize> {
-    let path = Path::new("tables/edges2_permutations.json");
-    let file = File::open(&path).unwrap();
-
-    let perms: HashMap<String, usize> = serde_json::from_reader(&file).unwrap();
-
-    perms
-}
-
-fn edges1_mask() -> HashSet<Edge> {
-    let mut mask = HashSet::new();
-
-    mask.insert(Edge::RedWhite);
-    mask.insert(Edge::RedYellow);
-    mask.insert(Edge::GreenOrange);
-    mask.insert(Edge::GreenWhite);
-    mask.insert(Edge::GreenYellow);
-    mask.insert(Edge::GreenRed);
-
-    mask
-}
-
-fn edges2_mask() -> HashSet<Edge> {
-    let mut mask = HashSet::new();
-
-    mask.insert(Edge::BlueOrange);
-    mask.insert(Edge::BlueWhite);
-    mask.insert(Edge::BlueYellow);
-    mask.insert(Edge::BlueRed);
-    mask.insert(Edge::OrangeWhite);
-    mask.insert(Edge::OrangeYellow);
-
-    mask
-}
-
-// which slice contains blue/green, up/down, right/left, or front/back
+// which facelet of the corner, counting clockwise from the U/D facelet, is blue/green
 #[derive(Debug)]
 enum CornerOrientation {
     Up,
@@ -120,7 +100,6 @@ enum CornerOrientation {
 }
 
 impl CornerOrientation {
-    // fix this with unreachable!
     fn from(v: [u8; 3]) -> Option<CornerOrientation> {
         match v {
             [0, _, _] | [5, _, _] => Some(CornerOrientation::Up),
@@ -139,11 +118,6 @@ impl CornerOrientation {
     }
 }
 
-enum EdgeSet {
-    Edges1,
-    Edges2,
-}
-
 #[derive(Debug, PartialEq, Eq, Hash)]
 enum Edge {
     BlueOrange,
@@ -158,98 +132,24 @@ enum Edge {
     GreenWhite,
     GreenYellow,
     GreenRed,
-    Ignored, // special type for ignoring pieces for generating tables
 }
 
 impl Edge {
-    // clean this up with a macro
-    fn from(v: [u8; 2], mask: &HashSet<Edge>) -> Edge {
+    fn from(v: [u8; 2]) -> Option<Edge> {
         match v {
-            [0, 4] => {
-                if mask.contains(&Edge::BlueOrange) {
-                    Edge::Ignored
-                } else {
-                    Edge::BlueOrange
-                }
-            }
-            [0, 1] => {
-                if mask.contains(&Edge::BlueWhite) {
-                    Edge::Ignored
-                } else {
-                    Edge::BlueWhite
-                }
-            }
-            [0, 3] => {
-                if mask.contains(&Edge::BlueYellow) {
-                    Edge::Ignored
-                } else {
-                    Edge::BlueYellow
-                }
-            }
-            [0, 2] => {
-                if mask.contains(&Edge::BlueRed) {
-                    Edge::Ignored
-                } else {
-                    Edge::BlueRed
-                }
-            }
-            [1, 4] => {
-                if mask.contains(&Edge::OrangeWhite) {
-                    Edge::Ignored
-                } else {
-                    Edge::OrangeWhite
-                }
-            }
-            [3, 4] => {
-                if mask.contains(&Edge::OrangeYellow) {
-                    Edge::Ignored
-                } else {
-                    Edge::OrangeYellow
-                }
-            }
-            [1, 2] => {
-                if mask.contains(&Edge::RedWhite) {
-                    Edge::Ignored
-                } else {
-                    Edge::RedWhite
-                }
-            }
-            [2, 3] => {
-                if mask.contains(&Edge::RedYellow) {
-                    Edge::Ignored
-                } else {
-                    Edge::RedYellow
-                }
-            }
-            [4, 5] => {
-                if mask.contains(&Edge::GreenOrange) {
-                    Edge::Ignored
-                } else {
-                    Edge::GreenOrange
-                }
-            }
-            [1, 5] => {
-                if mask.contains(&Edge::GreenWhite) {
-                    Edge::Ignored
-                } else {
-                    Edge::GreenWhite
-                }
-            }
-            [3, 5] => {
-                if mask.contains(&Edge::GreenYellow) {
-                    Edge::Ignored
-                } else {
-                    Edge::GreenYellow
-                }
-            }
-            [2, 5] => {
-                if mask.contains(&Edge::GreenRed) {
-                    Edge::Ignored
-                } else {
-                    Edge::GreenRed
-                }
-            }
-            _ => unreachable!(),
+            [0, 4] => Some(Edge::BlueOrange),
+            [0, 1] => Some(Edge::BlueWhite),
+            [0, 3] => Some(Edge::BlueYellow),
+            [0, 2] => Some(Edge::BlueRed),
+            [1, 4] => Some(Edge::OrangeWhite),
+            [3, 4] => Some(Edge::OrangeYellow),
+            [1, 2] => Some(Edge::RedWhite),
+            [2, 3] => Some(Edge::RedYellow),
+            [4, 5] => Some(Edge::GreenOrange),
+            [1, 5] => Some(Edge::GreenWhite),
+            [3, 5] => Some(Edge::GreenYellow),
+            [2, 5] => Some(Edge::GreenRed),
+            _ => None,
         }
     }
 
@@ -267,7 +167,6 @@ impl Edge {
             Edge::GreenWhite => 9,
             Edge::GreenYellow => 10,
             Edge::GreenRed => 11,
-            Edge::Ignored => 12,
         }
     }
 }
@@ -279,8 +178,8 @@ enum EdgeOrientation {
 }
 
 impl EdgeOrientation {
-    fn from(v: [u8; 2]) -> EdgeOrientation {
-        match v {
+    fn from(v: [u8; 2]) -> Option<EdgeOrientation> {
+        let o = match v {
             [0, 4]
             | [0, 1]
             | [0, 3]
@@ -305,8 +204,10 @@ impl EdgeOrientation {
             | [1, 5]
             | [3, 5]
             | [2, 5] => EdgeOrientation::Bad,
-            _ => unreachable!(),
-        }
+            _ => return None,
+        };
+
+        Some(o)
     }
 
     fn index(&self) -> usize {
@@ -317,63 +218,161 @@ impl EdgeOrientation {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct Cube<'a> {
-    transformations: &'a HashMap<String, Array2<u8>>,
-    data: Array1<u8>,
+/// A cube as the colors of its facelets, face by face.
+///
+/// The move definitions are shared by every cube of the same size, so a `Cube` holds nothing but
+/// its facelets, inline, and is copied, stored and sent between threads without allocating.
+#[derive(Clone, Copy, Debug)]
+pub struct Cube {
+    transformations: &'static HashMap<Move, Permutation>,
+    dim: u8,
+    // only the first `6 * dim * dim` are used
+    data: [u8; MAX_FACELETS],
 }
 
-impl<'a> Cube<'a> {
-    pub fn new(dim: u32, transformations: &'a HashMap<String, Array2<u8>>) -> Self {
-        let mut v = Vec::new();
-        for color in 0..6 {
-            for _i in 0..dim.pow(2) {
-                v.push(color);
-            }
+impl Cube {
+    pub fn new(dim: u32) -> Self {
+        let transformations: &'static HashMap<Move, Permutation> = match dim {
+            2 => &CUBE2,
+            3 => &CUBE3,
+            _ => panic!("no move definitions for a {}x{} cube", dim, dim),
+        };
+
+        let mut data = [0; MAX_FACELETS];
+        let per_face = dim.pow(2) as usize;
+        for (i, x) in data[..6 * per_face].iter_mut().enumerate() {
+            *x = (i / per_face) as u8;
         }
 
         Cube {
             transformations,
-            data: Array1::from(v),
+            dim: dim as u8,
+            data,
         }
     }
 
-    pub fn corners(transformations: &'a HashMap<String, Array2<u8>>) -> Self {
-        let mut c = Cube::new(3, transformations);
+    // the facelets actually on the cube
+    fn facelets(&self) -> &[u8] {
+        &self.data[..6 * self.dim as usize * self.dim as usize]
+    }
+
+    pub fn corners() -> Self {
+        let mut c = Cube::new(3);
         let indices = vec![
             1, 3, 4, 5, 7, 10, 12, 13, 14, 16, 19, 21, 22, 23, 25, 28, 30, 31, 32, 34, 37, 39, 40,
             41, 43, 46, 48, 49, 50, 52,
         ];
         for i in indices.into_iter() {
-            c.data[[i]] = 255;
+            c.data[i] = 255;
         }
 
         c
     }
 
-    pub fn edges1(transformations: &'a HashMap<String, Array2<u8>>) -> Self {
-        let mut c = Cube::new(3, transformations);
+    pub fn edges1() -> Self {
+        let mut c = Cube::new(3);
         let indices = vec![
             0, 2, 4, 6, 8, 9, 11, 12, 13, 15, 16, 17, 18, 20, 22, 24, 25, 26, 27, 29, 31, 32, 33,
             34, 35, 36, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53,
         ];
         for i in indices.into_iter() {
-            c.data[[i]] = 255;
+            c.data[i] = 255;
         }
 
         c
     }
 
-    pub fn twist(&mut self, moves: &str) {
-        let moves = String::from(moves);
+    /// Builds a 3x3 cube from its 54 facelets in the URFDLB order used by Kociemba's solver.
+    ///
+    /// Faces are listed U, R, F, D, L, B, each read left to right and top to bottom with U on
+    /// top (B on top for U, F on top for D). Any six distinct characters may be used for the
+    /// colors, e.g. `W`/`Y`/`G`/`B`/`R`/`O`; the center of each face decides which color
+    /// belongs to it. Whitespace is ignored. This only checks the shape of the string; use
+    /// `validate` to check that the cube can actually be solved.
+    pub fn from_facelets(facelets: &str) -> Result<Self, FaceletError> {
+        let chars: Vec<char> = facelets.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.len() != 54 {
+            return Err(FaceletError::WrongLength(chars.len()));
+        }
 
-        for mv in moves.split_whitespace() {
-            self.data = self.transformations[mv].dot(&self.data);
+        // color of each face in `data` order
+        let mut centers = ['\0'; 6];
+        for (k, &face) in FACELET_ORDER.iter().enumerate() {
+            let center = chars[k * 9 + 4];
+            if centers.contains(&center) {
+                return Err(FaceletError::DuplicateCenter(center));
+            }
+            centers[face] = center;
         }
+
+        let mut c = Cube::new(3);
+        for (k, &face) in FACELET_ORDER.iter().enumerate() {
+            for i in 0..9 {
+                let position = k * 9 + i;
+                let color = centers.iter().position(|&x| x == chars[position]).ok_or(
+                    FaceletError::UnknownColor {
+                        position,
+                        color: chars[position],
+                    },
+                )?;
+                c.data[face * 9 + i] = color as u8;
+            }
+        }
+
+        for (face, &center) in centers.iter().enumerate() {
+            let count = c.facelets().iter().filter(|&&x| x as usize == face).count();
+            if count != 9 {
+                return Err(FaceletError::WrongCount {
+                    color: center,
+                    count,
+                });
+            }
+        }
+
+        Ok(c)
+    }
+
+    /// Writes a 3x3 cube as 54 facelets in the URFDLB order accepted by `from_facelets`, using
+    /// the face letters as colors. Gives `None` for other cubes, and for cubes with facelets
+    /// whose color isn't that of exactly one center, like the masked ones of `from_pattern`.
+    pub fn to_facelets(&self) -> Option<String> {
+        if self.dim != 3 {
+            return None;
+        }
+
+        let mut face_of = [None; 256];
+        for face in 0..6 {
+            let center = &mut face_of[self.data[face * 9 + 4] as usize];
+            if center.is_some() {
+                return None;
+            }
+            *center = Some(face);
+        }
+
+        let mut result = String::with_capacity(54);
+        for &face in FACELET_ORDER.iter() {
+            for &color in self.data[face * 9..(face + 1) * 9].iter() {
+                result.push(FACE_LETTERS[face_of[color as usize]?]);
+            }
+        }
+
+        Some(result)
+    }
+
+    pub fn twist(&mut self, moves: &Algorithm) {
+        for mv in moves {
+            self.apply(*mv);
+        }
+    }
+
+    pub fn apply(&mut self, mv: Move) {
+        let len = self.facelets().len();
+        self.transformations[&mv].apply(&mut self.data[..len]);
     }
 
     pub fn is_solved(&self) -> bool {
-        for face in self.data.to_vec().chunks(9) {
+        let facelets = self.facelets();
+        for face in facelets.chunks(facelets.len() / 6) {
             let color = face[0];
             for c in face.iter() {
                 if c != &color {
@@ -386,183 +385,256 @@ impl<'a> Cube<'a> {
     }
 
     pub fn state_string(&self) -> String {
-        let mut color_table: HashMap<u8, u8> = HashMap::new();
+        let mut color_table = [u8::MAX; 256];
         let mut counter = 0;
         base64::encode(
             &self
-                .data
-                .to_vec()
-                .into_iter()
-                .map(|x| {
-                    if color_table.contains_key(&x) {
-                        *color_table.get(&x).unwrap()
-                    } else {
-                        color_table.insert(x, counter);
+                .facelets()
+                .iter()
+                .map(|&x| {
+                    if color_table[x as usize] == u8::MAX {
+                        color_table[x as usize] = counter;
                         counter += 1;
-                        *color_table.get(&x).unwrap()
                     }
+                    color_table[x as usize]
                 })
                 .collect::<Vec<u8>>()[..],
         )
     }
 
-    // XXX clean this up with a macro
-    fn corners_data(&self) -> (Vec<Corner>, Vec<CornerOrientation>) {
-        let mut perm = Vec::new();
-        let mut orient = Vec::new();
-
-        let mut c = vec![self.data[0], self.data[9], self.data[38]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[2], self.data[29], self.data[36]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[6], self.data[11], self.data[18]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[8], self.data[27], self.data[20]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[51], self.data[15], self.data[44]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[53], self.data[35], self.data[42]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[45], self.data[17], self.data[24]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        c = vec![self.data[47], self.data[33], self.data[26]];
-        orient.push(CornerOrientation::from([c[0], c[1], c[2]]).unwrap());
-        c.sort();
-        perm.push(Corner::from([c[0], c[1], c[2]]).unwrap());
-
-        (perm, orient)
-    }
-
-    // XXX clean this up with a macro
-    fn edges_data(&self, mask: &HashSet<Edge>) -> (Vec<Edge>, Vec<EdgeOrientation>) {
-        let mut perm = Vec::new();
-        let mut orient = Vec::new();
-
-        let mut c = vec![self.data[1], self.data[37]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[3], self.data[10]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[5], self.data[28]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[7], self.data[19]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[41], self.data[12]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[39], self.data[32]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[21], self.data[14]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[23], self.data[30]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[52], self.data[43]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[48], self.data[16]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[50], self.data[34]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        let mut c = vec![self.data[46], self.data[25]];
-        orient.push(EdgeOrientation::from([c[0], c[1]]));
-        c.sort();
-        perm.push(Edge::from([c[0], c[1]], &mask));
-
-        (perm, orient)
-    }
-
-    pub fn successors(&self) -> Vec<IDAStarNode<'a>> {
-        let mut result = Vec::new();
-        let all_moves = vec![
-            "U", "U'", "U2", "F", "F'", "F2", "R", "R'", "R2", "D", "D'", "D2", "B", "B'", "B2",
-            "L", "L'", "L2",
-        ];
-
-        for m in all_moves.into_iter() {
-            let mut c = self.clone();
-            c.twist(m);
+    /// Checks that a 3x3 cube could be reached from solved by turning faces, i.e. that it was
+    /// scanned or entered correctly.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.to_cubies().map(|_| ())
+    }
 
-            result.push(IDAStarNode {
-                state: c,
-                mv_to_get_here: m.to_string(),
+    /// Reads the corner and edge cubies off a 3x3 cube's facelets, failing if they don't make up
+    /// a solvable cube.
+    pub fn to_cubies(&self) -> Result<CubieCube, ValidationError> {
+        if self.dim != 3 {
+            return Err(ValidationError::NotThreeByThree {
+                dim: self.dim as u32,
             });
         }
 
-        result
+        for face in 0..6 {
+            let color = self.data[face * 9 + 4];
+            let count = self.facelets().iter().filter(|&&x| x == color).count();
+            if count != 9 {
+                return Err(ValidationError::StickerCount { color, count });
+            }
+        }
+
+        // the centers never move, so they tell us which face each color belongs to
+        let mut face_of = [0; 256];
+        for face in 0..6 {
+            face_of[self.data[face * 9 + 4] as usize] = face as u8;
+        }
+        let face = |i: usize| face_of[self.data[i] as usize];
+
+        let mut c = CubieCube::solved();
+
+        for (i, facelets) in CORNER_FACELETS.iter().enumerate() {
+            let mut v = [face(facelets[0]), face(facelets[1]), face(facelets[2])];
+            let orientation = CornerOrientation::from(v);
+            v.sort();
+            match (Corner::from(v), orientation) {
+                // read from its U or D facelet, the corner has to go round clockwise like it
+                // does at home, or it's a mirror image of a real corner
+                (Some(corner), Some(orientation))
+                    if (0..3).all(|k| {
+                        let home = CORNER_FACELETS[corner.index()][k] / 9;
+                        face(facelets[(k + orientation.index()) % 3]) as usize == home
+                    }) =>
+                {
+                    c.cp[i] = corner.index() as u8;
+                    c.co[i] = orientation.index() as u8;
+                }
+                _ => return Err(ValidationError::ImpossibleCorner { position: i }),
+            }
+        }
+
+        for (i, facelets) in EDGE_FACELETS.iter().enumerate() {
+            let mut v = [face(facelets[0]), face(facelets[1])];
+            let orientation = EdgeOrientation::from(v);
+            v.sort();
+            match (Edge::from(v), orientation) {
+                (Some(edge), Some(orientation)) => {
+                    c.ep[i] = edge.index() as u8;
+                    c.eo[i] = orientation.index() as u8;
+                }
+                _ => return Err(ValidationError::ImpossibleEdge { position: i }),
+            }
+        }
+
+        c.validate()?;
+
+        Ok(c)
+    }
+
+    /// Builds the 3x3 cube with the given cubies, colored like `Cube::new`.
+    pub fn from_cubies(c: &CubieCube) -> Self {
+        let mut cube = Cube::new(3);
+
+        for (i, facelets) in CORNER_FACELETS.iter().enumerate() {
+            let home = &CORNER_FACELETS[c.cp[i] as usize];
+            for (k, &facelet) in facelets.iter().enumerate() {
+                let from = home[(k + 3 - c.co[i] as usize) % 3];
+                cube.data[facelet] = (from / 9) as u8;
+            }
+        }
+
+        for (i, facelets) in EDGE_FACELETS.iter().enumerate() {
+            let home = &EDGE_FACELETS[c.ep[i] as usize];
+            for (k, &facelet) in facelets.iter().enumerate() {
+                let from = home[(k + c.eo[i] as usize) % 2];
+                cube.data[facelet] = (from / 9) as u8;
+            }
+        }
+
+        cube
+    }
+
+    /// A cube belonging to the pattern `index` of `spec`, showing only the pieces it tracks.
+    /// Everything else, centers included, is masked out like `Cube::corners`.
+    pub fn from_pattern(spec: &PatternSpec, index: usize) -> Self {
+        let c = spec.from_index(index);
+        let mut cube = Cube::from_cubies(&c);
+        for (i, facelets) in CORNER_FACELETS.iter().enumerate() {
+            if !spec.tracks_corner(c.cp[i]) {
+                for &facelet in facelets {
+                    cube.data[facelet] = 255;
+                }
+            }
+        }
+        for (i, facelets) in EDGE_FACELETS.iter().enumerate() {
+            if !spec.tracks_edge(c.ep[i]) {
+                for &facelet in facelets {
+                    cube.data[facelet] = 255;
+                }
+            }
+        }
+        for center in (4..54).step_by(9) {
+            cube.data[center] = 255;
+        }
+
+        cube
     }
 }
 
-impl<'a> Hash for Cube<'a> {
+/// Why a facelet string couldn't be read as a cube.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FaceletError {
+    /// There weren't exactly 54 facelets.
+    WrongLength(usize),
+    /// Two faces have a center of the same color.
+    DuplicateCenter(char),
+    /// A facelet has a color that isn't the center of any face.
+    UnknownColor { position: usize, color: char },
+    /// A color doesn't appear on exactly 9 facelets.
+    WrongCount { color: char, count: usize },
+}
+
+impl fmt::Display for FaceletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaceletError::WrongLength(n) => write!(f, "expected 54 facelets, found {}", n),
+            FaceletError::DuplicateCenter(c) => {
+                write!(f, "more than one center has color `{}`", c)
+            }
+            FaceletError::UnknownColor { position, color } => write!(
+                f,
+                "facelet {} has color `{}`, which is not the color of any center",
+                position, color
+            ),
+            FaceletError::WrongCount { color, count } => write!(
+                f,
+                "color `{}` appears on {} facelets instead of 9",
+                color, count
+            ),
+        }
+    }
+}
+
+impl error::Error for FaceletError {}
+
+/// Why a cube can't be solved.
+///
+/// Corner and edge positions are numbered as in `CubieCube`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Only 3x3 cubes are made of the corners and edges checked here; this one is `dim`x`dim`.
+    NotThreeByThree { dim: u32 },
+    /// A center's color doesn't appear on exactly 9 facelets.
+    StickerCount { color: u8, count: usize },
+    /// The facelets at a corner position don't belong to any corner.
+    ImpossibleCorner { position: usize },
+    /// The facelets at an edge position don't belong to any edge.
+    ImpossibleEdge { position: usize },
+    /// The same corner appears at two positions.
+    DuplicateCorner { first: usize, second: usize },
+    /// The same edge appears at two positions.
+    DuplicateEdge { first: usize, second: usize },
+    /// The corner twists don't add up to a whole turn; a single corner is twisted in place.
+    CornerTwist,
+    /// An odd number of edges are flipped.
+    EdgeFlip,
+    /// The corner and edge permutations have different parity, e.g. two edges are swapped.
+    PermutationParity,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::NotThreeByThree { dim } => {
+                write!(f, "a {}x{} cube isn't a 3x3 cube", dim, dim)
+            }
+            ValidationError::StickerCount { color, count } => write!(
+                f,
+                "color {} appears on {} facelets instead of 9",
+                color, count
+            ),
+            ValidationError::ImpossibleCorner { position } => {
+                write!(f, "corner {} has colors no corner has", position)
+            }
+            ValidationError::ImpossibleEdge { position } => {
+                write!(f, "edge {} has colors no edge has", position)
+            }
+            ValidationError::DuplicateCorner { first, second } => {
+                write!(f, "corners {} and {} are the same piece", first, second)
+            }
+            ValidationError::DuplicateEdge { first, second } => {
+                write!(f, "edges {} and {} are the same piece", first, second)
+            }
+            ValidationError::CornerTwist => write!(f, "a corner is twisted"),
+            ValidationError::EdgeFlip => write!(f, "an edge is flipped"),
+            ValidationError::PermutationParity => write!(f, "two pieces are swapped"),
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl Hash for Cube {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.data.hash(state);
+        self.facelets().hash(state);
     }
 }
 
-impl<'a> PartialEq for Cube<'a> {
+impl PartialEq for Cube {
     fn eq(&self, other: &Cube) -> bool {
-        self.data == other.data
+        self.facelets() == other.facelets()
     }
 }
 
-impl<'a> Eq for Cube<'a> {}
+impl Eq for Cube {}
 
 pub fn corners_state(c: &Cube) -> String {
     let mut color_table: HashMap<u8, u8> = HashMap::new();
     let mut counter = 0;
 
     let mut data_to_encode: Vec<u8> = Vec::new();
-    for val in c.data.to_vec().into_iter() {
+    for &val in c.facelets().iter() {
         if val != 255 {
             data_to_encode.push(val);
         }
@@ -572,13 +644,10 @@ pub fn corners_state(c: &Cube) -> String {
         &data_to_encode
             .into_iter()
             .map(|x| {
-                if color_table.contains_key(&x) {
-                    *color_table.get(&x).unwrap()
-                } else {
-                    color_table.insert(x, counter);
+                *color_table.entry(x).or_insert_with(|| {
                     counter += 1;
-                    *color_table.get(&x).unwrap()
-                }
+                    counter - 1
+                })
             })
             .collect::<Vec<u8>>()[..],
     )
@@ -590,7 +659,7 @@ pub fn edges_state(c: &Cube) -> String {
     let mut counter = 0;
 
     let mut data_to_encode: Vec<u8> = Vec::new();
-    for val in c.data.to_vec().into_iter() {
+    for &val in c.facelets().iter() {
         if val != 255 {
             data_to_encode.push(val);
         }
@@ -600,142 +669,212 @@ pub fn edges_state(c: &Cube) -> String {
         &data_to_encode
             .into_iter()
             .map(|x| {
-                if color_table.contains_key(&x) {
-                    *color_table.get(&x).unwrap()
-                } else {
-                    color_table.insert(x, counter);
+                *color_table.entry(x).or_insert_with(|| {
                     counter += 1;
-                    *color_table.get(&x).unwrap()
-                }
+                    counter - 1
+                })
             })
             .collect::<Vec<u8>>()[..],
     )
 }
 
-fn corner_perm_as_string(perm: &[Corner]) -> String {
-    perm.iter()
-        .map(|x| x.index().to_string())
-        .collect::<Vec<String>>()
-        .join(",")
-}
-
-fn edges_perm_as_string(perm: &[Edge]) -> String {
-    perm.iter()
-        .map(|x| x.index().to_string())
-        .collect::<Vec<String>>()
-        .join(",")
-}
-
-pub fn corners_index(c: &Cube) -> usize {
-    let (perm, orient) = c.corners_data();
-
-    let perm_index = CORNER_PERMUTATIONS
-        .get(&corner_perm_as_string(&perm))
-        .unwrap();
-
-    let orient_index = {
-        let mut result = 0 as usize;
-
-        // intentionally skip one, last orientation is governed by other 7
-        for i in 0..7 {
-            result += orient[i].index() * (3 as usize).pow(i as u32);
-        }
-
-        result
-    };
-
-    (perm_index * 2187) + orient_index
-}
+#[cfg(test)]
+mod tests {
+    use crate::cube::{Cube, FaceletError, ValidationError};
+    use crate::moves::Algorithm;
+    use crate::pdb::{CORNERS, EDGES1, EDGES2};
 
-fn edges_index(c: &Cube, set: EdgeSet) -> usize {
-    let (perm, orient) = match &set {
-        EdgeSet::Edges1 => c.edges_data(&EDGES1_MASK),
-        EdgeSet::Edges2 => c.edges_data(&EDGES2_MASK),
-    };
-
-    let perm_index = match &set {
-        EdgeSet::Edges1 => EDGES1_PERMUTATIONS
-            .get(&edges_perm_as_string(&perm))
-            .unwrap(),
-        EdgeSet::Edges2 => EDGES2_PERMUTATIONS
-            .get(&edges_perm_as_string(&perm))
-            .unwrap(),
-    };
-
-    let orient_index = {
-        let mut result = 0 as usize;
-
-        let mut power = 0;
-        // only consider orientations of non-ignored
-        for (i, o) in orient.iter().enumerate() {
-            if perm[i] != Edge::Ignored {
-                result += o.index() * (2 as usize).pow(power);
-                power += 1;
-            }
-        }
+    #[test]
+    fn test_cube2() {
+        let scramble: Algorithm = "B U2 B R2 D F2 B' U' L2".parse().unwrap();
+        let mut c = Cube::new(2);
+        c.twist(&scramble);
+        assert!(!c.is_solved());
+        c.twist(&scramble.inverse());
+        assert!(c.is_solved());
+    }
 
-        result
-    };
+    #[test]
+    fn test_cube3() {
+        let scramble: Algorithm = "U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B"
+            .parse()
+            .unwrap();
+        let mut c = Cube::new(3);
+        c.twist(&scramble);
+        assert!(!c.is_solved());
+        assert_eq!(Cube::from_cubies(&c.to_cubies().unwrap()), c);
+        c.twist(&scramble.inverse());
+        assert_eq!(c, Cube::new(3));
+    }
 
-    (perm_index * 64) + orient_index
-}
+    #[test]
+    fn test_corners_data() {
+        let c = Cube::new(3).to_cubies().unwrap();
+        assert_eq!(CORNERS.index(&c), 0);
 
-pub fn edges1_index(c: &Cube) -> usize {
-    edges_index(c, EdgeSet::Edges1)
-}
+        let mut c = Cube::new(3);
+        c.twist(&"U L".parse().unwrap());
+        assert_eq!(CORNERS.index(&c.to_cubies().unwrap()), 44384618);
+    }
 
-pub fn edges2_index(c: &Cube) -> usize {
-    edges_index(c, EdgeSet::Edges2)
-}
+    #[test]
+    fn test_edges_data() {
+        let solved = EDGES2.index(&Cube::new(3).to_cubies().unwrap());
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashSet;
+        let mut c = Cube::new(3);
+        c.twist(&"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap());
+        assert_ne!(EDGES2.index(&c.to_cubies().unwrap()), solved);
 
-    use crate::cube::{Cube, Edge};
-    use crate::transformations;
+        // these moves put edges 6 to 11 back, though not the others
+        c.twist(&"U2 R' F' L' B2 D' F L2".parse().unwrap());
+        assert_eq!(EDGES2.index(&c.to_cubies().unwrap()), solved);
+        assert!(!c.is_solved());
+    }
 
     #[test]
-    fn it_works() {
-        let t = transformations::cube2();
-        let mut c = Cube::new(2, &t);
-        c.twist("B U2 B R2 D F2 B' U' L2");
-        println!("{:?}", c.data);
+    fn test_is_solved() {
+        let mut c = Cube::new(3);
+        assert!(c.is_solved());
+        c.twist(&"R".parse().unwrap());
+        assert!(!c.is_solved());
+        c.twist(&"R'".parse().unwrap());
+        assert!(c.is_solved());
+        c.twist(&"U U'".parse().unwrap());
+        assert!(c.is_solved());
     }
 
     #[test]
-    fn test_cube3() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B");
-        println!("{:?}", c.data);
+    fn test_send_between_threads() {
+        let mut c = Cube::new(3);
+        c.twist(&"R U R' U'".parse().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            c.twist(&"U R U' R'".parse().unwrap());
+            c
+        });
+
+        assert!(handle.join().unwrap().is_solved());
+        // the thread had a copy
+        assert!(!c.is_solved());
     }
 
     #[test]
-    fn test_corners_data() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        //c.twist("U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B");
-        //c.twist("U L");
-        let data = c.corners_data();
-        println!("{:?}, {:?}", data.0, data.1);
-        println!("{}", crate::cube::corners_index(&c));
+    fn test_facelets() {
+        let solved = "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB";
+        assert_eq!(Cube::from_facelets(solved).unwrap(), Cube::new(3));
+
+        let mut c = Cube::new(3);
+        c.twist(&"R".parse().unwrap());
+        let facelets = "UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB";
+        assert_eq!(c.to_facelets().as_deref(), Some(facelets));
+        assert_eq!(Cube::new(2).to_facelets(), None);
+        let masked = Cube::from_pattern(&CORNERS.spec, 0);
+        assert_eq!(masked.to_facelets(), None);
+        assert_eq!(Cube::from_facelets(facelets).unwrap(), c);
+
+        let colors = facelets
+            .chars()
+            .map(|x| match x {
+                'U' => 'W',
+                'R' => 'R',
+                'F' => 'G',
+                'D' => 'Y',
+                'L' => 'O',
+                _ => 'B',
+            })
+            .collect::<String>();
+        assert_eq!(Cube::from_facelets(&colors).unwrap(), c);
     }
 
     #[test]
-    fn test_edges_data() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B U2 R' F' L' B2 D' F L2");
+    fn test_facelets_errors() {
+        assert_eq!(
+            Cube::from_facelets("UUU"),
+            Err(FaceletError::WrongLength(3))
+        );
+        assert_eq!(
+            Cube::from_facelets("UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBX"),
+            Err(FaceletError::UnknownColor {
+                position: 53,
+                color: 'X'
+            })
+        );
+        assert_eq!(
+            Cube::from_facelets("UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBU"),
+            Err(FaceletError::WrongCount {
+                color: 'U',
+                count: 10
+            })
+        );
+    }
 
-        println!("{}", crate::cube::edges2_index(&c));
+    #[test]
+    fn test_validate() {
+        let mut c = Cube::new(3);
+        c.twist(&"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap());
+        assert_eq!(c.validate(), Ok(()));
+
+        assert_eq!(
+            Cube::new(2).validate(),
+            Err(ValidationError::NotThreeByThree { dim: 2 })
+        );
+
+        let mut bad = Cube::new(3);
+        bad.data[0] = 1;
+        assert_eq!(
+            bad.validate(),
+            Err(ValidationError::StickerCount { color: 0, count: 8 })
+        );
+
+        let mut bad = c;
+        bad.data.swap(0, 10);
+        assert_eq!(
+            bad.validate(),
+            Err(ValidationError::ImpossibleCorner { position: 0 })
+        );
+
+        // a mirror image of a corner, whose colors are those of a real one
+        let mut bad = Cube::new(3);
+        bad.data.swap(36, 47);
+        assert_eq!(
+            bad.validate(),
+            Err(ValidationError::ImpossibleCorner { position: 1 })
+        );
+
+        let mut bad = c;
+        let (a, b, c2) = (bad.data[8], bad.data[27], bad.data[20]);
+        bad.data[8] = b;
+        bad.data[27] = c2;
+        bad.data[20] = a;
+        assert_eq!(bad.validate(), Err(ValidationError::CornerTwist));
+
+        let mut bad = c;
+        bad.data.swap(1, 37);
+        assert_eq!(bad.validate(), Err(ValidationError::EdgeFlip));
+
+        let mut bad = c;
+        bad.data.swap(1, 3);
+        bad.data.swap(37, 10);
+        assert_eq!(bad.validate(), Err(ValidationError::PermutationParity));
     }
 
     #[test]
-    fn test_is_solved() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("U U'");
-        println!("cube is solved: {}", c.is_solved());
+    fn test_from_index() {
+        let mut c = Cube::new(3);
+        c.twist(&"F2 L' U B2 R D' R2 U' L B".parse().unwrap());
+        let cubies = c.to_cubies().unwrap();
+
+        let [corners, edges1, edges2] =
+            [&CORNERS, &EDGES1, &EDGES2].map(|t| Cube::from_pattern(&t.spec, t.index(&cubies)));
+
+        // every sticker is shown by exactly one of the masked cubes, in its scrambled color
+        for i in (0..54).filter(|i| i % 9 != 4) {
+            let shown: Vec<u8> = [&corners, &edges1, &edges2]
+                .iter()
+                .map(|m| m.data[i])
+                .filter(|&x| x != 255)
+                .collect();
+            assert_eq!(shown, vec![c.data[i]]);
+        }
     }
 }
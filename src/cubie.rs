@@ -0,0 +1,223 @@
+use lazy_static::lazy_static;
+
+use crate::cube::{Cube, ValidationError};
+use crate::moves::{Algorithm, Move};
+
+lazy_static! {
+    static ref MOVES: Vec<CubieCube> = move_table();
+}
+
+// each move applied to a solved cube, read off the facelet definitions in `transformations`
+fn move_table() -> Vec<CubieCube> {
+    Move::ALL
+        .iter()
+        .map(|m| {
+            let mut c = Cube::new(3);
+            c.apply(*m);
+            c.to_cubies().unwrap()
+        })
+        .collect()
+}
+
+/// A 3x3 cube as the position and orientation of its corner and edge cubies.
+///
+/// `cp[i]` is the corner sitting in corner position `i` and `co[i]` its twist, and likewise
+/// `ep`/`eo` for edges. Centers never move, so they aren't stored. Applying a move is a handful
+/// of array lookups, which is what the searches and table generators use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CubieCube {
+    pub cp: [u8; 8],
+    pub co: [u8; 8],
+    pub ep: [u8; 12],
+    pub eo: [u8; 12],
+}
+
+impl CubieCube {
+    pub fn solved() -> Self {
+        CubieCube {
+            cp: [0, 1, 2, 3, 4, 5, 6, 7],
+            co: [0; 8],
+            ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            eo: [0; 12],
+        }
+    }
+
+    /// The cube reached by applying `other` to this cube.
+    pub fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut result = *self;
+
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+
+        result
+    }
+
+    /// The cube that `multiply` undoes this one with. It's as far from solved, its solutions
+    /// being this cube's scrambles.
+    pub fn inverse(&self) -> CubieCube {
+        let mut result = *self;
+
+        for i in 0..8 {
+            let piece = self.cp[i] as usize;
+            result.cp[piece] = i as u8;
+            result.co[piece] = (3 - self.co[i]) % 3;
+        }
+
+        for i in 0..12 {
+            let piece = self.ep[i] as usize;
+            result.ep[piece] = i as u8;
+            result.eo[piece] = self.eo[i];
+        }
+
+        result
+    }
+
+    pub fn apply(&mut self, mv: Move) {
+        *self = self.multiply(&MOVES[mv.index()]);
+    }
+
+    pub fn twist(&mut self, moves: &Algorithm) {
+        for mv in moves {
+            self.apply(*mv);
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        *self == CubieCube::solved()
+    }
+
+    /// Checks that every piece appears once and that the twist, flip and parity constraints of
+    /// a real cube hold.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some((first, second)) = duplicate(&self.cp) {
+            return Err(ValidationError::DuplicateCorner { first, second });
+        }
+        if let Some((first, second)) = duplicate(&self.ep) {
+            return Err(ValidationError::DuplicateEdge { first, second });
+        }
+
+        if self.co.iter().map(|&x| x as usize).sum::<usize>() % 3 != 0 {
+            return Err(ValidationError::CornerTwist);
+        }
+        if self.eo.iter().map(|&x| x as usize).sum::<usize>() % 2 != 0 {
+            return Err(ValidationError::EdgeFlip);
+        }
+
+        if parity(&self.cp) != parity(&self.ep) {
+            return Err(ValidationError::PermutationParity);
+        }
+
+        Ok(())
+    }
+}
+
+// positions of the first repeated piece, if any
+fn duplicate(perm: &[u8]) -> Option<(usize, usize)> {
+    for i in 0..perm.len() {
+        for j in (i + 1)..perm.len() {
+            if perm[i] == perm[j] {
+                return Some((i, j));
+            }
+        }
+    }
+
+    None
+}
+
+/// 0 if `perm` is an even permutation, 1 if odd.
+pub(crate) fn parity(perm: &[u8]) -> u8 {
+    let mut inversions = 0;
+    for i in 0..perm.len() {
+        for j in (i + 1)..perm.len() {
+            if perm[i] > perm[j] {
+                inversions += 1;
+            }
+        }
+    }
+
+    inversions % 2
+}
+
+impl Default for CubieCube {
+    fn default() -> Self {
+        CubieCube::solved()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cube::{Cube, ValidationError};
+    use crate::cubie::CubieCube;
+    use crate::moves::Algorithm;
+
+    #[test]
+    fn test_moves_match_facelets() {
+        let alg: Algorithm = "U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B"
+            .parse()
+            .unwrap();
+
+        let mut facelets = Cube::new(3);
+        let mut cubies = CubieCube::solved();
+        for mv in &alg {
+            facelets.apply(*mv);
+            cubies.apply(*mv);
+            assert_eq!(facelets.to_cubies(), Ok(cubies));
+        }
+
+        assert_eq!(Cube::from_cubies(&cubies), facelets);
+    }
+
+    #[test]
+    fn test_inverse_solves() {
+        let alg: Algorithm = "B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+        assert!(!c.is_solved());
+        c.twist(&alg.inverse());
+        assert!(c.is_solved());
+    }
+
+    #[test]
+    fn test_inverse_cube() {
+        let alg: Algorithm = "U F' R2 U2 R B' R2 B R U L2".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+        assert!(c.multiply(&c.inverse()).is_solved());
+
+        let mut inverse = CubieCube::solved();
+        inverse.twist(&alg.inverse());
+        assert_eq!(c.inverse(), inverse);
+    }
+
+    #[test]
+    fn test_validate_duplicates() {
+        let mut c = CubieCube::solved();
+        c.cp[5] = 2;
+        assert_eq!(
+            c.validate(),
+            Err(ValidationError::DuplicateCorner {
+                first: 2,
+                second: 5
+            })
+        );
+
+        let mut c = CubieCube::solved();
+        c.ep[11] = 0;
+        assert_eq!(
+            c.validate(),
+            Err(ValidationError::DuplicateEdge {
+                first: 0,
+                second: 11
+            })
+        );
+    }
+}
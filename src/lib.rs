@@ -1,3 +1,14 @@
 pub mod cube;
+pub mod cubie;
+pub mod limits;
+pub mod moves;
+pub mod packed;
+pub mod pattern;
+pub mod pdb;
+pub mod rank;
 pub mod search;
+pub mod symmetry;
+pub mod table_file;
+pub mod thistlethwaite;
 pub mod transformations;
+pub mod two_phase;
@@ -0,0 +1,260 @@
+//! Stopping searches early, and hearing how they're going.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::search::SearchError;
+
+/// A flag for stopping a search from elsewhere, e.g. another thread. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+    parent: Option<Box<CancellationToken>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// A token cancelled along with this one, which can also be cancelled by itself.
+    pub fn child(&self) -> Self {
+        CancellationToken {
+            flag: Arc::default(),
+            parent: Some(Box::new(self.clone())),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|p| p.is_cancelled())
+    }
+}
+
+/// Why a search stopped before it was done.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Cancelled,
+    Deadline,
+    NodeBudget,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Cancelled => write!(f, "cancelled"),
+            StopReason::Deadline => write!(f, "out of time"),
+            StopReason::NodeBudget => write!(f, "out of nodes"),
+        }
+    }
+}
+
+/// How far a search got, as reported after each iteration and when it's stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The bound of the iteration: the IDA* bound, which no solution is shorter than, the
+    /// breadth first depth, or the phase 1 depth of `two_phase`.
+    pub bound: u8,
+    /// Nodes expanded in the iteration.
+    pub iteration_nodes: u64,
+    /// Nodes expanded in all.
+    pub nodes_expanded: u64,
+    pub elapsed: Duration,
+}
+
+type ProgressFn<'a> = Box<dyn FnMut(&Progress) + Send + 'a>;
+
+/// When a search should give up, and what to tell about how it's going. The default sets no
+/// limits.
+#[derive(Default)]
+pub struct Limits<'a> {
+    cancel: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    max_nodes: Option<u64>,
+    progress: Option<ProgressFn<'a>>,
+    // the node count of all the workers of a parallel search, see `Tracker::worker`
+    shared_nodes: Option<Arc<AtomicU64>>,
+}
+
+impl<'a> Limits<'a> {
+    pub fn new() -> Self {
+        Limits::default()
+    }
+
+    /// Stops the search once `token` is cancelled.
+    pub fn cancel_with(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the deadline `timeout` from now.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+
+    /// Stops the search after it expands `max_nodes` nodes.
+    pub fn max_nodes(mut self, max_nodes: u64) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Calls `f` at the end of each iteration of the search.
+    pub fn on_progress<F: FnMut(&Progress) + Send + 'a>(mut self, f: F) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+}
+
+/// Counts the nodes a search expands, reports its progress and stops it when its limits say so.
+pub(crate) struct Tracker<'l, 'a> {
+    limits: &'l mut Limits<'a>,
+    start: Instant,
+    deadline: Option<Instant>,
+    bound: u8,
+    iteration_start: u64,
+    nodes_expanded: u64,
+    // how many of the nodes have been added to the shared count
+    shared: u64,
+}
+
+impl<'l, 'a> Tracker<'l, 'a> {
+    pub(crate) fn new(limits: &'l mut Limits<'a>) -> Self {
+        Tracker {
+            deadline: limits.deadline,
+            limits,
+            start: Instant::now(),
+            bound: 0,
+            iteration_start: 0,
+            nodes_expanded: 0,
+            shared: 0,
+        }
+    }
+
+    /// The limits of a worker searching part of this search's tree on another thread: those of
+    /// this search with no progress reports, and also stopped by `stop`. Workers add up their
+    /// nodes in `nodes` a batch at a time, so together they may go a little over the budget.
+    pub(crate) fn worker(
+        &self,
+        stop: &CancellationToken,
+        nodes: &Arc<AtomicU64>,
+    ) -> Limits<'static> {
+        Limits {
+            cancel: Some(stop.clone()),
+            deadline: self.deadline,
+            max_nodes: self.limits.max_nodes,
+            progress: None,
+            shared_nodes: Some(nodes.clone()),
+        }
+    }
+
+    /// A token to stop the workers of this search with, cancelled along with its own.
+    pub(crate) fn stop_token(&self) -> CancellationToken {
+        self.limits
+            .cancel
+            .as_ref()
+            .map_or_else(CancellationToken::new, |t| t.child())
+    }
+
+    /// Stops the search at `deadline` if that's before the deadline it has.
+    pub(crate) fn stop_by(&mut self, deadline: Instant) {
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+    }
+
+    pub(crate) fn bound(&self) -> u8 {
+        self.bound
+    }
+
+    pub(crate) fn nodes_expanded(&self) -> u64 {
+        self.nodes_expanded
+    }
+
+    /// Counts `nodes` expanded by workers.
+    pub(crate) fn add_nodes(&mut self, nodes: u64) {
+        self.nodes_expanded += nodes;
+    }
+
+    /// Adds the nodes not yet in the shared count of a parallel search to it, returning the total.
+    pub(crate) fn flush(&mut self) -> u64 {
+        let batch = self.nodes_expanded - self.shared;
+        self.shared = self.nodes_expanded;
+        match &self.limits.shared_nodes {
+            Some(shared) => shared.fetch_add(batch, Ordering::Relaxed) + batch,
+            None => self.nodes_expanded,
+        }
+    }
+
+    pub(crate) fn stopped(&self, reason: StopReason) -> SearchError {
+        SearchError::Stopped {
+            reason,
+            progress: self.progress(),
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub(crate) fn start_iteration(&mut self, bound: u8) {
+        self.bound = bound;
+        self.iteration_start = self.nodes_expanded;
+    }
+
+    pub(crate) fn finish_iteration(&mut self) {
+        let progress = self.progress();
+        if let Some(f) = &mut self.limits.progress {
+            f(&progress);
+        }
+    }
+
+    /// Counts a node, failing if the search should stop instead of expanding it.
+    pub(crate) fn expand(&mut self) -> Result<(), SearchError> {
+        match self.stop_reason() {
+            Some(reason) => Err(self.stopped(reason)),
+            None => {
+                self.nodes_expanded += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn stop_reason(&mut self) -> Option<StopReason> {
+        // looking at the clock, or at the count shared with other threads, every node would take
+        // longer than the node
+        let batch_done = self.nodes_expanded.is_multiple_of(1024);
+        let nodes = match self.limits.shared_nodes {
+            None => Some(self.nodes_expanded),
+            Some(_) if batch_done => Some(self.flush()),
+            Some(_) => None,
+        };
+
+        let limits = &self.limits;
+        if limits.cancel.as_ref().is_some_and(|t| t.is_cancelled()) {
+            Some(StopReason::Cancelled)
+        } else if limits.max_nodes.zip(nodes).is_some_and(|(max, n)| n >= max) {
+            Some(StopReason::NodeBudget)
+        } else if batch_done && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(StopReason::Deadline)
+        } else {
+            None
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bound: self.bound,
+            iteration_nodes: self.nodes_expanded - self.iteration_start,
+            nodes_expanded: self.nodes_expanded,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
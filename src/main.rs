@@ -0,0 +1,241 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::{env, io};
+
+use cube_solver::packed::Packing;
+use cube_solver::pdb;
+use cube_solver::pdb::{PatternDatabase, Table};
+
+fn usage() -> String {
+    let tables: Vec<&str> = pdb::TABLES.iter().map(|t| t.name).collect();
+
+    format!(
+        "usage:
+    cube-solver pdb generate <table> [--output PATH] [--threads N] [--packing P]
+    cube-solver pdb verify <table> [--input PATH] [--sample N]
+    cube-solver pdb inspect <table> [--input PATH]
+    cube-solver pdb compress <table> [--input PATH] [--output PATH] [--packing P]
+
+<table> is one of {tables}.
+
+Paths default to the tables directory, which is $CUBE_SOLVER_TABLES or ./tables. generate writes
+<table>.pdb, which the solver maps into memory as is, and compress turns it into the smaller
+<table>.data.gz, which the solver falls back to. Both write gzipped bincode to paths ending in
+.gz and the .pdb format otherwise. --packing is one of byte (the default), nibble or mod3,
+storing one, two or four entries per byte.",
+        tables = tables.join(", ")
+    )
+}
+
+struct Options {
+    table: &'static Table,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    sample: Option<usize>,
+    threads: Option<usize>,
+    packing: Option<Packing>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let name = args.first().ok_or("missing table name")?;
+        let table = pdb::table(name).ok_or_else(|| format!("unknown table {}", name))?;
+
+        let mut options = Options {
+            table,
+            input: None,
+            output: None,
+            sample: None,
+            threads: None,
+            packing: None,
+        };
+
+        let mut rest = args[1..].iter();
+        while let Some(flag) = rest.next() {
+            let value = rest
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--input" => options.input = Some(PathBuf::from(value)),
+                "--output" => options.output = Some(PathBuf::from(value)),
+                "--sample" => {
+                    let n = value
+                        .parse()
+                        .map_err(|_| format!("bad sample size {}", value))?;
+                    options.sample = Some(n);
+                }
+                "--threads" => {
+                    let n = value
+                        .parse()
+                        .map_err(|_| format!("bad thread count {}", value))?;
+                    options.threads = Some(n);
+                }
+                "--packing" => {
+                    let packing = Packing::ALL
+                        .iter()
+                        .find(|p| p.name() == value)
+                        .ok_or_else(|| format!("unknown packing {}", value))?;
+                    options.packing = Some(*packing);
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn table_path(&self) -> PathBuf {
+        pdb::tables_dir().join(self.table.file_name)
+    }
+
+    fn compressed_path(&self) -> PathBuf {
+        pdb::tables_dir().join(self.table.compressed_file_name)
+    }
+
+    fn load(&self) -> Result<PatternDatabase, Box<dyn Error>> {
+        let db = match &self.input {
+            Some(path) => PatternDatabase::open(self.table, path)?,
+            None => PatternDatabase::load(self.table, pdb::tables_dir())?,
+        };
+        eprintln!(
+            "loaded {}, {}",
+            self.table.name,
+            if db.entries().is_mapped() {
+                "mapped"
+            } else {
+                "read into memory"
+            }
+        );
+
+        Ok(db)
+    }
+}
+
+fn generate(options: &Options) -> Result<(), Box<dyn Error>> {
+    let table = options.table;
+    let threads = options
+        .threads
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+    eprintln!("generating {} with {} threads", table.name, threads);
+
+    let data = pdb::generate(table, threads, |depth, filled| {
+        eprintln!("depth {}: {} of {} filled", depth, filled, table.size())
+    });
+
+    let path = options
+        .output
+        .clone()
+        .unwrap_or_else(|| options.table_path());
+    let db = PatternDatabase::new(table, data)?;
+    db.pack(options.packing.unwrap_or(Packing::Byte))?
+        .save(&path)?;
+    eprintln!("wrote {}", path.display());
+
+    Ok(())
+}
+
+fn verify(options: &Options) -> Result<(), Box<dyn Error>> {
+    let db = options.load()?;
+    let size = options.table.size();
+    let step = options.sample.map_or(1, |n| (size / n.max(1)).max(1));
+
+    let mut bad = 0usize;
+    let mut checked = 0usize;
+    let mut last_percent = None;
+    for index in (0..size).step_by(step) {
+        if !db.is_consistent(index) {
+            if bad < 10 {
+                eprintln!("\rentry {} is inconsistent", index);
+            }
+            bad += 1;
+        }
+        checked += 1;
+
+        let percent = index * 100 / size;
+        if last_percent != Some(percent) {
+            eprint!("\rverifying {}%", percent);
+            io::stderr().flush()?;
+            last_percent = Some(percent);
+        }
+    }
+    eprintln!("\rverified {} entries", checked);
+
+    if bad > 0 {
+        return Err(format!("{} of {} entries are inconsistent", bad, checked).into());
+    }
+
+    Ok(())
+}
+
+fn inspect(options: &Options) -> Result<(), Box<dyn Error>> {
+    let db = options.load()?;
+    let histogram = db.histogram();
+    let total = db.entries().len();
+
+    println!(
+        "{}: {} entries, {} packed",
+        options.table.name,
+        total,
+        db.entries().packing()
+    );
+    for (depth, &count) in histogram.iter().enumerate() {
+        println!(
+            "{:>3} {:>12} {:>6.2}%",
+            depth,
+            count,
+            count as f64 * 100.0 / total as f64
+        );
+    }
+
+    Ok(())
+}
+
+fn compress(options: &Options) -> Result<(), Box<dyn Error>> {
+    let input = options
+        .input
+        .clone()
+        .unwrap_or_else(|| options.table_path());
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| options.compressed_path());
+
+    eprintln!("loading {}", input.display());
+    let mut db = PatternDatabase::open(options.table, &input)?;
+    if let Some(packing) = options.packing {
+        db = db.pack(packing)?;
+    }
+    db.save(&output)?;
+    eprintln!("wrote {}", output.display());
+
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (command, rest) = match args {
+        [pdb, command, rest @ ..] if pdb == "pdb" => (command, rest),
+        _ => return Err(usage().into()),
+    };
+    let options = Options::parse(rest).map_err(|e| format!("{}\n\n{}", e, usage()))?;
+
+    match command.as_str() {
+        "generate" => generate(&options),
+        "verify" => verify(&options),
+        "inspect" => inspect(&options),
+        "compress" => compress(&options),
+        _ => Err(format!("unknown command {}\n\n{}", command, usage()).into()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,343 @@
+use std::error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    U,
+    F,
+    R,
+    D,
+    B,
+    L,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::U, Face::F, Face::R, Face::D, Face::B, Face::L];
+
+    pub fn index(self) -> usize {
+        match self {
+            Face::U => 0,
+            Face::F => 1,
+            Face::R => 2,
+            Face::D => 3,
+            Face::B => 4,
+            Face::L => 5,
+        }
+    }
+
+    /// The face across the cube from this one.
+    pub fn opposite(self) -> Face {
+        Face::ALL[(self.index() + 3) % 6]
+    }
+
+    fn from_char(c: char) -> Option<Face> {
+        match c {
+            'U' => Some(Face::U),
+            'F' => Some(Face::F),
+            'R' => Some(Face::R),
+            'D' => Some(Face::D),
+            'B' => Some(Face::B),
+            'L' => Some(Face::L),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Face {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            Face::U => "U",
+            Face::F => "F",
+            Face::R => "R",
+            Face::D => "D",
+            Face::B => "B",
+            Face::L => "L",
+        };
+
+        write!(f, "{}", c)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Clockwise,
+    CounterClockwise,
+    Half,
+}
+
+impl Turn {
+    pub const ALL: [Turn; 3] = [Turn::Clockwise, Turn::CounterClockwise, Turn::Half];
+
+    pub fn index(self) -> usize {
+        match self {
+            Turn::Clockwise => 0,
+            Turn::CounterClockwise => 1,
+            Turn::Half => 2,
+        }
+    }
+
+    pub fn inverse(self) -> Turn {
+        match self {
+            Turn::Clockwise => Turn::CounterClockwise,
+            Turn::CounterClockwise => Turn::Clockwise,
+            Turn::Half => Turn::Half,
+        }
+    }
+}
+
+/// A single outer face turn in the half-turn metric.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub face: Face,
+    pub turn: Turn,
+}
+
+impl Move {
+    /// All 18 face turns, ordered by face and then by turn.
+    pub const ALL: [Move; 18] = [
+        Move::new(Face::U, Turn::Clockwise),
+        Move::new(Face::U, Turn::CounterClockwise),
+        Move::new(Face::U, Turn::Half),
+        Move::new(Face::F, Turn::Clockwise),
+        Move::new(Face::F, Turn::CounterClockwise),
+        Move::new(Face::F, Turn::Half),
+        Move::new(Face::R, Turn::Clockwise),
+        Move::new(Face::R, Turn::CounterClockwise),
+        Move::new(Face::R, Turn::Half),
+        Move::new(Face::D, Turn::Clockwise),
+        Move::new(Face::D, Turn::CounterClockwise),
+        Move::new(Face::D, Turn::Half),
+        Move::new(Face::B, Turn::Clockwise),
+        Move::new(Face::B, Turn::CounterClockwise),
+        Move::new(Face::B, Turn::Half),
+        Move::new(Face::L, Turn::Clockwise),
+        Move::new(Face::L, Turn::CounterClockwise),
+        Move::new(Face::L, Turn::Half),
+    ];
+
+    pub const fn new(face: Face, turn: Turn) -> Self {
+        Move { face, turn }
+    }
+
+    /// Position of this move in `Move::ALL`.
+    pub fn index(self) -> usize {
+        self.face.index() * 3 + self.turn.index()
+    }
+
+    pub fn inverse(self) -> Move {
+        Move::new(self.face, self.turn.inverse())
+    }
+
+    /// Whether this move may come right after `prev` in a canonical sequence, i.e. one with no
+    /// two turns of the same face in a row and turns of opposite faces, which commute, in one
+    /// order only. Every sequence has a canonical one at most as long doing the same thing.
+    pub fn can_follow(self, prev: Move) -> bool {
+        self.face != prev.face
+            && (self.face != prev.face.opposite() || self.face.index() > prev.face.index())
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.turn {
+            Turn::Clockwise => write!(f, "{}", self.face),
+            Turn::CounterClockwise => write!(f, "{}'", self.face),
+            Turn::Half => write!(f, "{}2", self.face),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Move, ParseError> {
+        let invalid = || ParseError {
+            position: 0,
+            token: s.to_string(),
+        };
+
+        let mut chars = s.chars();
+        let face = chars.next().and_then(Face::from_char).ok_or_else(invalid)?;
+        let turn = match chars.as_str() {
+            "" => Turn::Clockwise,
+            "'" => Turn::CounterClockwise,
+            "2" => Turn::Half,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Move::new(face, turn))
+    }
+}
+
+/// A sequence of moves, e.g. a scramble or a solution.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Algorithm {
+    pub moves: Vec<Move>,
+}
+
+impl Algorithm {
+    pub fn new() -> Self {
+        Algorithm { moves: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.moves.push(mv);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.moves.iter()
+    }
+
+    /// The algorithm that undoes this one.
+    pub fn inverse(&self) -> Algorithm {
+        Algorithm {
+            moves: self.moves.iter().rev().map(|m| m.inverse()).collect(),
+        }
+    }
+}
+
+impl From<Vec<Move>> for Algorithm {
+    fn from(moves: Vec<Move>) -> Self {
+        Algorithm { moves }
+    }
+}
+
+impl FromIterator<Move> for Algorithm {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        Algorithm {
+            moves: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Algorithm {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.iter()
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, mv) in self.moves.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", mv)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Algorithm, ParseError> {
+        let mut moves = Vec::new();
+        for (position, token) in s.split_whitespace().enumerate() {
+            let mv = token
+                .parse::<Move>()
+                .map_err(|e| ParseError { position, ..e })?;
+            moves.push(mv);
+        }
+
+        Ok(Algorithm { moves })
+    }
+}
+
+/// A token that is not a valid move in standard notation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Index of the offending token, counting whitespace separated moves from zero.
+    pub position: usize,
+    pub token: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid move `{}` at position {}",
+            self.token, self.position
+        )
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::{Algorithm, Face, Move, ParseError, Turn};
+
+    #[test]
+    fn test_parse_display_roundtrip() {
+        let s = "B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B";
+        let alg: Algorithm = s.parse().unwrap();
+        assert_eq!(alg.len(), 15);
+        assert_eq!(alg.moves[0], Move::new(Face::B, Turn::CounterClockwise));
+        assert_eq!(alg.to_string(), s);
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let err = "R U R' U3 R".parse::<Algorithm>().unwrap_err();
+        assert_eq!(
+            err,
+            ParseError {
+                position: 3,
+                token: String::from("U3"),
+            }
+        );
+
+        assert!("r".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn test_opposite() {
+        assert_eq!(Face::U.opposite(), Face::D);
+        assert_eq!(Face::L.opposite(), Face::R);
+        for &face in Face::ALL.iter() {
+            assert_eq!(face.opposite().opposite(), face);
+        }
+    }
+
+    #[test]
+    fn test_can_follow() {
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+        assert!(!mv("U2").can_follow(mv("U'")));
+        assert!(mv("D").can_follow(mv("U")));
+        assert!(!mv("U").can_follow(mv("D")));
+        assert!(mv("R").can_follow(mv("U")));
+
+        let after = |s: &str| Move::ALL.iter().filter(|m| m.can_follow(mv(s))).count();
+        assert_eq!(after("U"), 15);
+        assert_eq!(after("D2"), 12);
+
+        // 18 moves to start with, and then 15 after U, F and R but 12 after D, B and L
+        let pairs = Move::ALL
+            .iter()
+            .flat_map(|&prev| Move::ALL.iter().filter(move |mv| mv.can_follow(prev)))
+            .count();
+        assert_eq!(pairs, 9 * 15 + 9 * 12);
+    }
+
+    #[test]
+    fn test_move_index() {
+        for (i, mv) in Move::ALL.iter().enumerate() {
+            assert_eq!(mv.index(), i);
+        }
+    }
+}
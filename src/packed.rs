@@ -0,0 +1,219 @@
+//! Pattern database entries stored several to a byte.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+/// How a table's entries are laid out in its bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Packing {
+    /// One entry per byte.
+    Byte,
+    /// Two entries per byte, low nibble first. Distances have to be at most 15.
+    Nibble,
+    /// Four entries per byte, lowest bits first, each holding only its distance mod 3. That is
+    /// enough to recover the distance by walking down to solved, see `PatternDatabase::distance`.
+    Mod3,
+}
+
+impl Packing {
+    pub const ALL: [Packing; 3] = [Packing::Byte, Packing::Nibble, Packing::Mod3];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Packing::Byte => "byte",
+            Packing::Nibble => "nibble",
+            Packing::Mod3 => "mod3",
+        }
+    }
+
+    /// Bytes needed to store `entries` entries.
+    pub fn bytes_for(self, entries: usize) -> usize {
+        match self {
+            Packing::Byte => entries,
+            Packing::Nibble => entries.div_ceil(2),
+            Packing::Mod3 => entries.div_ceil(4),
+        }
+    }
+
+    fn bits(self) -> usize {
+        match self {
+            Packing::Byte => 8,
+            Packing::Nibble => 4,
+            Packing::Mod3 => 2,
+        }
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// where the packed bytes live: read into memory, or in a mapped table file
+#[derive(Clone)]
+enum Bytes {
+    Owned(Vec<u8>),
+    Mapped { map: Arc<Mmap>, offset: usize },
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Mapped { map, offset } => &map[*offset..],
+        }
+    }
+}
+
+/// A table of small numbers, packed according to a `Packing`.
+#[derive(Clone)]
+pub struct PackedTable {
+    packing: Packing,
+    len: usize,
+    bytes: Bytes,
+}
+
+impl PackedTable {
+    /// Takes one distance per byte as is.
+    pub fn unpacked(entries: Vec<u8>) -> Self {
+        PackedTable {
+            packing: Packing::Byte,
+            len: entries.len(),
+            bytes: Bytes::Owned(entries),
+        }
+    }
+
+    /// Packs one distance per byte into `packing`. Fails with the first distance that doesn't
+    /// fit in a nibble.
+    pub fn pack(entries: &[u8], packing: Packing) -> Result<Self, u8> {
+        if packing == Packing::Byte {
+            return Ok(PackedTable {
+                packing,
+                len: entries.len(),
+                bytes: Bytes::Owned(entries.to_vec()),
+            });
+        }
+
+        let per_byte = 8 / packing.bits();
+        let mut bytes = vec![0; packing.bytes_for(entries.len())];
+        for (i, &d) in entries.iter().enumerate() {
+            let value = match packing {
+                Packing::Nibble if d > 15 => return Err(d),
+                Packing::Mod3 => d % 3,
+                _ => d,
+            };
+            bytes[i / per_byte] |= value << ((i % per_byte) * packing.bits());
+        }
+
+        Ok(PackedTable {
+            packing,
+            len: entries.len(),
+            bytes: Bytes::Owned(bytes),
+        })
+    }
+
+    /// Wraps bytes read back from a table file holding `len` entries, telling the packing apart
+    /// by how many bytes there are.
+    pub fn from_raw(bytes: Vec<u8>, len: usize) -> Option<Self> {
+        let packing = *Packing::ALL
+            .iter()
+            .find(|p| p.bytes_for(len) == bytes.len())?;
+
+        Some(PackedTable {
+            packing,
+            len,
+            bytes: Bytes::Owned(bytes),
+        })
+    }
+
+    /// Uses the `len` entries packed in `map` from `offset` on in place. The caller checks the
+    /// mapping is long enough.
+    pub(crate) fn mapped(map: Arc<Mmap>, offset: usize, packing: Packing, len: usize) -> Self {
+        PackedTable {
+            packing,
+            len,
+            bytes: Bytes::Mapped { map, offset },
+        }
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        match self.packing {
+            Packing::Byte => self.bytes[index],
+            packing => {
+                let per_byte = 8 / packing.bits();
+                let mask = (1 << packing.bits()) - 1;
+                (self.bytes[index / per_byte] >> ((index % per_byte) * packing.bits())) & mask
+            }
+        }
+    }
+
+    pub fn packing(&self) -> Packing {
+        self.packing
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.packing.bytes_for(self.len)]
+    }
+
+    /// Whether the entries live in a memory mapped file rather than in memory of their own.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.bytes, Bytes::Mapped { .. })
+    }
+}
+
+impl PartialEq for PackedTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.packing == other.packing && self.len == other.len && self.bytes() == other.bytes()
+    }
+}
+
+impl Eq for PackedTable {}
+
+impl fmt::Debug for PackedTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PackedTable")
+            .field("packing", &self.packing)
+            .field("len", &self.len)
+            .field("mapped", &self.is_mapped())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packed::{PackedTable, Packing};
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let entries: Vec<u8> = (0..1001).map(|i| (i * 7 % 13) as u8).collect();
+
+        for &packing in Packing::ALL.iter() {
+            let packed = PackedTable::pack(&entries, packing).unwrap();
+            assert_eq!(packed.bytes().len(), packing.bytes_for(entries.len()));
+
+            let read = PackedTable::from_raw(packed.bytes().to_vec(), entries.len()).unwrap();
+            assert_eq!(read, packed);
+
+            for (i, &d) in entries.iter().enumerate() {
+                let expected = if packing == Packing::Mod3 { d % 3 } else { d };
+                assert_eq!(read.get(i), expected);
+            }
+        }
+
+        assert_eq!(PackedTable::pack(&[3, 16, 2], Packing::Nibble), Err(16));
+    }
+}
@@ -0,0 +1,409 @@
+//! Describing a pattern database by which pieces it tracks, and numbering its patterns.
+
+use crate::cubie::{parity, CubieCube};
+use crate::rank;
+
+/// Which corners and edges a pattern database tracks, and what it knows about them. Everything
+/// else about a cube is ignored, so each entry stands for all the cubes that look the same once
+/// the untracked pieces are blanked out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PatternSpec {
+    /// Bit `i` set tracks corner `i`.
+    pub corners: u8,
+    /// Bit `i` set tracks edge `i`.
+    pub edges: u16,
+    /// Whether tracked pieces are told apart. If not, only which positions hold one counts.
+    pub permutation: bool,
+    /// Whether the twists and flips of tracked pieces count.
+    pub orientation: bool,
+}
+
+impl PatternSpec {
+    /// Tracks `corners` and `edges` with their permutation and orientation.
+    pub const fn new(corners: u8, edges: u16) -> Self {
+        assert!(edges < 1 << 12, "there are only 12 edges");
+        PatternSpec {
+            corners,
+            edges,
+            permutation: true,
+            orientation: true,
+        }
+    }
+
+    /// Tracks only where the pieces are, not how they're twisted or flipped.
+    pub const fn permutation_only(self) -> Self {
+        PatternSpec {
+            orientation: false,
+            ..self
+        }
+    }
+
+    /// Tracks only how the pieces are twisted or flipped and which positions hold them, not
+    /// which piece is which.
+    pub const fn orientation_only(self) -> Self {
+        PatternSpec {
+            permutation: false,
+            ..self
+        }
+    }
+
+    fn corner_orbit(&self) -> Orbit {
+        Orbit {
+            n: 8,
+            mask: self.corners as u16,
+            modulus: 3,
+            mirrored: &LEGACY_MIRRORED_CORNERS,
+            permutation: self.permutation,
+            orientation: self.orientation,
+        }
+    }
+
+    fn edge_orbit(&self) -> Orbit {
+        Orbit {
+            n: 12,
+            mask: self.edges,
+            modulus: 2,
+            mirrored: &[false; 12],
+            permutation: self.permutation,
+            orientation: self.orientation,
+        }
+    }
+
+    /// Number of patterns, i.e. the size of the table.
+    pub fn size(&self) -> usize {
+        self.corner_orbit().size() * self.edge_orbit().size()
+    }
+
+    /// The pattern `c` belongs to, numbered densely from 0.
+    pub fn index(&self, c: &CubieCube) -> usize {
+        let corners = self.corner_orbit().index(&c.cp, &c.co);
+        let edges = self.edge_orbit().index(&c.ep, &c.eo);
+
+        corners * self.edge_orbit().size() + edges
+    }
+
+    /// Number of ways the tracked pieces can be placed, ignoring their orientation.
+    pub fn permutation_size(&self) -> usize {
+        self.corner_orbit().perm_size() * self.edge_orbit().perm_size()
+    }
+
+    /// Number of ways the tracked pieces can be oriented once they're placed.
+    pub fn orientation_size(&self) -> usize {
+        self.corner_orbit().orient_size() * self.edge_orbit().orient_size()
+    }
+
+    /// Where the tracked pieces of `c` are, numbered from 0 to `permutation_size`.
+    pub fn permutation_index(&self, c: &CubieCube) -> usize {
+        let corners = self.corner_orbit().perm_index(&c.cp);
+        let edges = self.edge_orbit().perm_index(&c.ep);
+
+        corners * self.edge_orbit().perm_size() + edges
+    }
+
+    /// How the tracked pieces of `c` are oriented, numbered from 0 to `orientation_size`.
+    pub fn orientation_index(&self, c: &CubieCube) -> usize {
+        let corners = self.corner_orbit().orient_index(&c.cp, &c.co);
+        let edges = self.edge_orbit().orient_index(&c.ep, &c.eo);
+
+        corners * self.edge_orbit().orient_size() + edges
+    }
+
+    /// Some cube with the given `permutation_index` and `orientation_index`, built like
+    /// `from_index`.
+    pub fn from_parts(&self, permutation: usize, orientation: usize) -> CubieCube {
+        let (corner_orbit, edge_orbit) = (self.corner_orbit(), self.edge_orbit());
+        let corners = (permutation / edge_orbit.perm_size()) * corner_orbit.orient_size()
+            + orientation / edge_orbit.orient_size();
+        let edges = (permutation % edge_orbit.perm_size()) * edge_orbit.orient_size()
+            + orientation % edge_orbit.orient_size();
+
+        self.from_index(corners * edge_orbit.size() + edges)
+    }
+
+    /// Some cube belonging to the pattern `index`, with the untracked pieces placed so that it's
+    /// a legal cube. When both orbits are tracked in full with their permutation, half the
+    /// indexes have mismatched parities and belong to no legal cube; those give an illegal one.
+    pub fn from_index(&self, index: usize) -> CubieCube {
+        let mut c = CubieCube::solved();
+        let edge_size = self.edge_orbit().size();
+
+        self.corner_orbit()
+            .unrank(index / edge_size, &mut c.cp, &mut c.co);
+        self.edge_orbit()
+            .unrank(index % edge_size, &mut c.ep, &mut c.eo);
+
+        if parity(&c.cp) != parity(&c.ep) {
+            // swapping two untracked pieces (and their orientations) doesn't change the pattern
+            if let Some((i, j)) = self.edge_orbit().untracked_pair(&c.ep) {
+                c.ep.swap(i, j);
+                c.eo.swap(i, j);
+            } else if let Some((i, j)) = self.corner_orbit().untracked_pair(&c.cp) {
+                c.cp.swap(i, j);
+                c.co.swap(i, j);
+            }
+        }
+
+        c
+    }
+
+    pub fn tracks_corner(&self, corner: u8) -> bool {
+        self.corners & (1 << corner) != 0
+    }
+
+    pub fn tracks_edge(&self, edge: u8) -> bool {
+        self.edges & (1 << edge) != 0
+    }
+}
+
+// the original tables were generated reading corner twists off facelets in this order, which is
+// counterclockwise for these positions
+const LEGACY_MIRRORED_CORNERS: [bool; 8] = [false, true, true, false, true, false, false, true];
+
+// the corners or the edges, as seen by one spec
+struct Orbit {
+    n: usize,
+    mask: u16,
+    modulus: u8,
+    // positions whose orientation is counted the other way round in the index
+    mirrored: &'static [bool],
+    permutation: bool,
+    orientation: bool,
+}
+
+impl Orbit {
+    // the orientation at `position` as counted in the index, and back again
+    fn mirror(&self, position: usize, o: u8) -> u8 {
+        if self.mirrored[position] {
+            (self.modulus - o) % self.modulus
+        } else {
+            o
+        }
+    }
+
+    // the tracked piece numbered `label` among them
+    fn piece(&self, label: u8) -> u8 {
+        (0..self.n as u8)
+            .filter(|&x| self.tracks(x))
+            .nth(label as usize)
+            .unwrap()
+    }
+
+    fn count(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    fn tracks(&self, piece: u8) -> bool {
+        self.mask & (1 << piece) != 0
+    }
+
+    fn perm_size(&self) -> usize {
+        if self.permutation {
+            rank::partial_count(self.n, self.count())
+        } else {
+            rank::combination_count(self.n, self.count())
+        }
+    }
+
+    // with every piece tracked, the last orientation follows from the others
+    fn free_orientations(&self) -> usize {
+        if !self.orientation {
+            0
+        } else if self.count() == self.n {
+            self.n - 1
+        } else {
+            self.count()
+        }
+    }
+
+    fn orient_size(&self) -> usize {
+        (self.modulus as usize).pow(self.free_orientations() as u32)
+    }
+
+    fn size(&self) -> usize {
+        self.perm_size() * self.orient_size()
+    }
+
+    fn index(&self, perm: &[u8], orient: &[u8]) -> usize {
+        self.perm_index(perm) * self.orient_size() + self.orient_index(perm, orient)
+    }
+
+    fn perm_index(&self, perm: &[u8]) -> usize {
+        // untracked pieces all look the same, tracked ones are numbered from 0 in order
+        let mut slots = [rank::BLANK; 12];
+        for (slot, &x) in slots.iter_mut().zip(perm.iter()) {
+            if self.tracks(x) {
+                *slot = (self.mask & ((1 << x) - 1)).count_ones() as u8;
+            }
+        }
+        let slots = &slots[..self.n];
+
+        if self.permutation {
+            rank::rank_partial(slots)
+        } else {
+            rank::rank_combination(slots)
+        }
+    }
+
+    // orientations of tracked pieces, in the order of the positions they're in
+    fn orient_index(&self, perm: &[u8], orient: &[u8]) -> usize {
+        let mut orient_index = 0;
+        let mut power = 1;
+        let tracked = (0..self.n).filter(|&i| self.tracks(perm[i]));
+        for i in tracked.take(self.free_orientations()) {
+            orient_index += self.mirror(i, orient[i]) as usize * power;
+            power *= self.modulus as usize;
+        }
+
+        orient_index
+    }
+
+    fn unrank(&self, index: usize, perm: &mut [u8], orient: &mut [u8]) {
+        let mut slots = [rank::BLANK; 12];
+        let slots = &mut slots[..self.n];
+        if self.permutation {
+            rank::unrank_partial(index / self.orient_size(), self.count(), slots);
+        } else {
+            // the tracked pieces aren't told apart, so put them in order
+            rank::unrank_combination(index / self.orient_size(), self.count(), slots);
+            let tracked = slots.iter_mut().filter(|s| **s != rank::BLANK);
+            for (label, slot) in tracked.enumerate() {
+                *slot = label as u8;
+            }
+        }
+
+        let mut untracked = (0..self.n as u8).filter(|&x| !self.tracks(x));
+        let mut orient_index = index % self.orient_size();
+        let mut free = self.free_orientations();
+        let mut total = 0;
+        let mut fix = None;
+        for (i, &slot) in slots.iter().enumerate() {
+            if slot == rank::BLANK {
+                perm[i] = untracked.next().unwrap();
+                orient[i] = 0;
+                fix.get_or_insert(i);
+            } else {
+                perm[i] = self.piece(slot);
+                orient[i] = 0;
+                if free > 0 {
+                    let o = (orient_index % self.modulus as usize) as u8;
+                    orient[i] = self.mirror(i, o);
+                    orient_index /= self.modulus as usize;
+                    free -= 1;
+                } else {
+                    // the last of a fully tracked orbit
+                    fix = Some(i);
+                }
+            }
+            total += orient[i] as usize;
+        }
+
+        // whatever twist or flip makes the orientations legal goes on one piece
+        if let Some(i) = fix {
+            let m = self.modulus as usize;
+            orient[i] = ((m - total % m) % m) as u8;
+        }
+    }
+
+    // two positions holding untracked pieces, if there are two
+    fn untracked_pair(&self, perm: &[u8]) -> Option<(usize, usize)> {
+        let mut positions = (0..self.n).rev().filter(|&i| !self.tracks(perm[i]));
+        let j = positions.next()?;
+        let i = positions.next()?;
+
+        Some((i, j))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cubie::CubieCube;
+    use crate::moves::Algorithm;
+    use crate::pattern::PatternSpec;
+
+    fn roundtrip(spec: PatternSpec, step: usize) {
+        for i in (0..spec.size()).step_by(step) {
+            let c = spec.from_index(i);
+            assert_eq!(c.validate(), Ok(()), "{:?} index {}", spec, i);
+            assert_eq!(spec.index(&c), i);
+        }
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(PatternSpec::new(0xff, 0).size(), 88179840);
+        assert_eq!(PatternSpec::new(0, 0x03f).size(), 42577920);
+        assert_eq!(PatternSpec::new(0, 0x07f).size(), 510935040);
+        assert_eq!(PatternSpec::new(0xff, 0).permutation_only().size(), 40320);
+        assert_eq!(PatternSpec::new(0xff, 0).orientation_only().size(), 2187);
+        assert_eq!(PatternSpec::new(0, 0xfff).orientation_only().size(), 2048);
+        assert_eq!(
+            PatternSpec::new(0, 0xf00).orientation_only().size(),
+            495 * 16
+        );
+    }
+
+    #[test]
+    fn test_index_roundtrip() {
+        roundtrip(PatternSpec::new(0xff, 0), 7919);
+        roundtrip(PatternSpec::new(0, 0x03f), 3989);
+        roundtrip(PatternSpec::new(0, 0xfc0), 3989);
+        roundtrip(PatternSpec::new(0, 0b1010_1101_0110), 40009);
+        roundtrip(PatternSpec::new(0x0f, 0x00f), 50021);
+        roundtrip(PatternSpec::new(0xff, 0).orientation_only(), 1);
+        roundtrip(PatternSpec::new(0, 0xfff).orientation_only(), 1);
+        roundtrip(PatternSpec::new(0, 0xf00).orientation_only(), 1);
+        roundtrip(PatternSpec::new(0x55, 0x0f0).permutation_only(), 97);
+    }
+
+    #[test]
+    fn test_parts() {
+        let alg: Algorithm = "L2 D B' R U2 F' D2 L".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+
+        for spec in [
+            PatternSpec::new(0xff, 0),
+            PatternSpec::new(0x0f, 0xf0f),
+            PatternSpec::new(0, 0xfff).orientation_only(),
+        ] {
+            assert_eq!(
+                spec.permutation_size() * spec.orientation_size(),
+                spec.size()
+            );
+            let (p, o) = (spec.permutation_index(&c), spec.orientation_index(&c));
+            assert_eq!(spec.index(&spec.from_parts(p, o)), spec.index(&c));
+        }
+    }
+
+    #[test]
+    fn test_legacy_indexes() {
+        // the numbering the shipped tables were generated with
+        let alg: Algorithm = "R U2 D' B D' L F2 U' R2 B' D L2 F".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+
+        assert_eq!(PatternSpec::new(0xff, 0).index(&c), 72779748);
+        assert_eq!(PatternSpec::new(0, 0x03f).index(&c), 20281111);
+        assert_eq!(PatternSpec::new(0, 0xfc0).index(&c), 24668809);
+    }
+
+    #[test]
+    fn test_untracked_pieces_ignored() {
+        let alg: Algorithm = "R U2 D' B D' L F2 U' R2 B' D L2 F".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+
+        let spec = PatternSpec::new(0b0011_0101, 0b1010_1101_0110);
+        let pattern = spec.from_index(spec.index(&c));
+        for i in 0..8 {
+            if spec.tracks_corner(c.cp[i]) {
+                assert_eq!((pattern.cp[i], pattern.co[i]), (c.cp[i], c.co[i]));
+            }
+        }
+        for i in 0..12 {
+            if spec.tracks_edge(c.ep[i]) {
+                assert_eq!((pattern.ep[i], pattern.eo[i]), (c.ep[i], c.eo[i]));
+            }
+        }
+    }
+}
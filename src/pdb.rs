@@ -0,0 +1,1354 @@
+use std::cmp;
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use lazy_static::lazy_static;
+use memmap2::Mmap;
+
+use crate::cubie::CubieCube;
+use crate::moves::Move;
+use crate::packed::{PackedTable, Packing};
+use crate::pattern::PatternSpec;
+use crate::symmetry::{SymmetryReduction, SYMMETRIES};
+use crate::table_file;
+
+/// Environment variable naming the directory `Heuristic::from_env` loads tables from.
+pub const TABLES_DIR_VAR: &str = "CUBE_SOLVER_TABLES";
+
+/// Directory tables are loaded from when `TABLES_DIR_VAR` isn't set.
+pub const DEFAULT_TABLES_DIR: &str = "tables";
+
+/// The directory named by `CUBE_SOLVER_TABLES`, or `tables` in the working directory if it
+/// isn't set.
+pub fn tables_dir() -> PathBuf {
+    env::var_os(TABLES_DIR_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_TABLES_DIR))
+}
+
+/// Describes one pattern database: where it lives on disk and which pieces it tracks, which
+/// decides how many entries it has and how a cube is mapped to its entry and back.
+pub struct Table {
+    pub name: &'static str,
+    /// The table in the format of `table_file`, which is mapped rather than read.
+    pub file_name: &'static str,
+    /// The gzipped table, which is smaller to ship but has to be decompressed into memory.
+    pub compressed_file_name: &'static str,
+    pub spec: PatternSpec,
+    pub cost: Cost,
+    /// If set, symmetric patterns share an entry, numbered by the reduction this returns.
+    pub symmetry: Option<fn() -> &'static SymmetryReduction>,
+}
+
+/// What a move costs when generating a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cost {
+    /// Every move costs one, so entries are numbers of moves.
+    Moves,
+    /// A move costs the number of tracked pieces it moves. Every face turn moves four corners and
+    /// four edges, so the entries of tables tracking disjoint pieces can be added up and divided
+    /// by four for each kind of piece to give a number of moves, see `Heuristic::additive`.
+    TrackedPieces,
+}
+
+impl Table {
+    pub fn size(&self) -> usize {
+        match self.symmetry {
+            Some(reduction) => reduction().size(),
+            None => self.spec.size(),
+        }
+    }
+
+    pub fn index(&self, c: &CubieCube) -> usize {
+        match self.symmetry {
+            Some(reduction) => reduction().index(c),
+            None => self.spec.index(c),
+        }
+    }
+
+    /// Some cube whose `index` is the given entry.
+    pub fn from_index(&self, index: usize) -> CubieCube {
+        match self.symmetry {
+            Some(reduction) => reduction().from_index(index),
+            None => self.spec.from_index(index),
+        }
+    }
+
+    /// The other entries of cubes symmetric to those of the entry `index`, which have to hold the
+    /// same distance.
+    pub fn symmetric_indexes(&self, index: usize) -> Vec<usize> {
+        match self.symmetry {
+            Some(reduction) => reduction().symmetric_indexes(index),
+            None => Vec::new(),
+        }
+    }
+
+    /// What the move taking `c` to `next` costs.
+    pub fn move_cost(&self, c: &CubieCube, next: &CubieCube) -> u8 {
+        match self.cost {
+            Cost::Moves => 1,
+            Cost::TrackedPieces => {
+                let corners = (0..8)
+                    .filter(|&i| next.cp[i] != c.cp[i] && self.spec.tracks_corner(next.cp[i]))
+                    .count();
+                let edges = (0..12)
+                    .filter(|&i| next.ep[i] != c.ep[i] && self.spec.tracks_edge(next.ep[i]))
+                    .count();
+
+                (corners + edges) as u8
+            }
+        }
+    }
+}
+
+pub const CORNERS: Table = Table {
+    name: "corners",
+    file_name: "corners.pdb",
+    compressed_file_name: "corners.data.gz",
+    spec: PatternSpec::new(0xff, 0),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 0 to 5.
+pub const EDGES1: Table = Table {
+    name: "edges1",
+    file_name: "edges1.pdb",
+    compressed_file_name: "edges1.data.gz",
+    spec: PatternSpec::new(0, 0x03f),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 6 to 11.
+pub const EDGES2: Table = Table {
+    name: "edges2",
+    file_name: "edges2.pdb",
+    compressed_file_name: "edges2.data.gz",
+    spec: PatternSpec::new(0, 0xfc0),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+// Larger edge groups make for a stronger heuristic at the cost of memory: a 7 edge table is 12 times
+// the size of a 6 edge one, an 8 edge table 120 times. The two groups of each size overlap, so
+// they're only used with `max`.
+
+/// Edges 0 to 6.
+pub const EDGES7A: Table = Table {
+    name: "edges7a",
+    file_name: "edges7a.pdb",
+    compressed_file_name: "edges7a.data.gz",
+    spec: PatternSpec::new(0, 0x07f),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 5 to 11.
+pub const EDGES7B: Table = Table {
+    name: "edges7b",
+    file_name: "edges7b.pdb",
+    compressed_file_name: "edges7b.data.gz",
+    spec: PatternSpec::new(0, 0xfe0),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 0 to 7.
+pub const EDGES8A: Table = Table {
+    name: "edges8a",
+    file_name: "edges8a.pdb",
+    compressed_file_name: "edges8a.data.gz",
+    spec: PatternSpec::new(0, 0x0ff),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 4 to 11.
+pub const EDGES8B: Table = Table {
+    name: "edges8b",
+    file_name: "edges8b.pdb",
+    compressed_file_name: "edges8b.data.gz",
+    spec: PatternSpec::new(0, 0xff0),
+    cost: Cost::Moves,
+    symmetry: None,
+};
+
+/// Edges 0 to 5, counting the edges moved rather than moves, to be added to `EDGES2_ADDITIVE`.
+pub const EDGES1_ADDITIVE: Table = Table {
+    name: "edges1-additive",
+    file_name: "edges1-additive.pdb",
+    compressed_file_name: "edges1-additive.data.gz",
+    spec: PatternSpec::new(0, 0x03f),
+    cost: Cost::TrackedPieces,
+    symmetry: None,
+};
+
+/// Edges 6 to 11, counting the edges moved rather than moves, to be added to `EDGES1_ADDITIVE`.
+pub const EDGES2_ADDITIVE: Table = Table {
+    name: "edges2-additive",
+    file_name: "edges2-additive.pdb",
+    compressed_file_name: "edges2-additive.data.gz",
+    spec: PatternSpec::new(0, 0xfc0),
+    cost: Cost::TrackedPieces,
+    symmetry: None,
+};
+
+// marks entries `generate` hasn't reached yet
+const UNVISITED: u8 = u8::MAX;
+
+// entries handed to a generator thread at a time
+const BLOCK: usize = 1 << 16;
+
+/// Fills `table` with the distance of every pattern from solved, one depth at a time, using
+/// `threads` threads. After each depth `progress` is called with it and the number of entries
+/// filled so far.
+pub fn generate<P: FnMut(u8, usize)>(table: &Table, threads: usize, progress: P) -> Vec<u8> {
+    let entries: Vec<AtomicU8> = (0..table.size())
+        .map(|_| AtomicU8::new(UNVISITED))
+        .collect();
+    entries[table.index(&CubieCube::solved())].store(0, Ordering::Relaxed);
+
+    match table.cost {
+        Cost::Moves => fill_by_moves(table, &entries, threads, progress),
+        Cost::TrackedPieces => fill_by_cost(table, &entries, threads, progress),
+    }
+
+    entries.into_iter().map(AtomicU8::into_inner).collect()
+}
+
+fn fill_by_moves<P: FnMut(u8, usize)>(
+    table: &Table,
+    entries: &[AtomicU8],
+    threads: usize,
+    mut progress: P,
+) {
+    let mut depth = 0;
+    let mut filled = 1;
+    progress(depth, filled);
+
+    loop {
+        // once most of the table is filled, looking for the few unvisited entries next to this
+        // depth is cheaper than expanding all of it
+        let backward = filled > table.size() / 2;
+        let found = in_blocks(table.size(), threads, |range| {
+            if backward {
+                fill_from_neighbors(table, entries, depth, range)
+            } else {
+                expand(table, entries, depth, range)
+            }
+        });
+
+        if found == 0 {
+            break;
+        }
+        depth += 1;
+        filled += found;
+        progress(depth, filled);
+    }
+}
+
+// With moves costing different amounts, an entry can first be reached by a path that isn't the
+// cheapest. Entries are lowered as cheaper paths turn up, and the ones at a depth are final once
+// every shallower depth has been expanded, since no move is free.
+fn fill_by_cost<P: FnMut(u8, usize)>(
+    table: &Table,
+    entries: &[AtomicU8],
+    threads: usize,
+    mut progress: P,
+) {
+    let deepest = AtomicU8::new(0);
+    let mut depth = 0;
+    let mut filled = 0;
+
+    loop {
+        let found = in_blocks(table.size(), threads, |range| {
+            relax(table, entries, depth, &deepest, range)
+        });
+        if found > 0 {
+            filled += found;
+            progress(depth, filled);
+        }
+
+        if depth >= deepest.load(Ordering::Relaxed) {
+            break;
+        }
+        depth += 1;
+    }
+}
+
+// runs `f` over blocks of `0..size` on `threads` threads, adding up what it returns
+fn in_blocks<F>(size: usize, threads: usize, f: F) -> usize
+where
+    F: Fn(Range<usize>) -> usize + Sync,
+{
+    let next_block = AtomicUsize::new(0);
+    let total = AtomicUsize::new(0);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                let start = next_block.fetch_add(BLOCK, Ordering::Relaxed);
+                if start >= size {
+                    break;
+                }
+                let end = cmp::min(start + BLOCK, size);
+                total.fetch_add(f(start..end), Ordering::Relaxed);
+            });
+        }
+    });
+
+    total.into_inner()
+}
+
+// marks the unvisited neighbors of entries at `depth` as one further, returning how many
+fn expand(table: &Table, entries: &[AtomicU8], depth: u8, range: Range<usize>) -> usize {
+    let mut found = 0;
+
+    for i in range {
+        if entries[i].load(Ordering::Relaxed) != depth {
+            continue;
+        }
+
+        let c = table.from_index(i);
+        for &mv in Move::ALL.iter() {
+            let mut next = c;
+            next.apply(mv);
+
+            let j = table.index(&next);
+            let filled = entries[j].compare_exchange(
+                UNVISITED,
+                depth + 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+            if filled.is_ok() {
+                found += 1;
+                for k in table.symmetric_indexes(j) {
+                    let filled = entries[k].compare_exchange(
+                        UNVISITED,
+                        depth + 1,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    );
+                    found += filled.is_ok() as usize;
+                }
+            }
+        }
+    }
+
+    found
+}
+
+// marks unvisited entries with a neighbor at `depth` as one further, returning how many
+fn fill_from_neighbors(
+    table: &Table,
+    entries: &[AtomicU8],
+    depth: u8,
+    range: Range<usize>,
+) -> usize {
+    let mut found = 0;
+
+    for i in range {
+        if entries[i].load(Ordering::Relaxed) != UNVISITED {
+            continue;
+        }
+
+        let c = table.from_index(i);
+        let reached = Move::ALL.iter().any(|&mv| {
+            let mut next = c;
+            next.apply(mv);
+            entries[table.index(&next)].load(Ordering::Relaxed) == depth
+        });
+        if reached {
+            entries[i].store(depth + 1, Ordering::Relaxed);
+            found += 1;
+        }
+    }
+
+    found
+}
+
+// lowers the neighbors of entries at `depth` to it plus the cost of the move there, keeping the
+// deepest entry set in `deepest` and returning how many entries are at `depth`
+fn relax(
+    table: &Table,
+    entries: &[AtomicU8],
+    depth: u8,
+    deepest: &AtomicU8,
+    range: Range<usize>,
+) -> usize {
+    let mut found = 0;
+
+    for i in range {
+        if entries[i].load(Ordering::Relaxed) != depth {
+            continue;
+        }
+        found += 1;
+
+        let c = table.from_index(i);
+        for &mv in Move::ALL.iter() {
+            let mut next = c;
+            next.apply(mv);
+
+            // a move that leaves every tracked piece alone leads back to the same entry
+            let cost = table.move_cost(&c, &next);
+            if cost == 0 {
+                continue;
+            }
+            let d = depth
+                .checked_add(cost)
+                .filter(|&d| d != UNVISITED)
+                .expect("distances should fit in a byte");
+            let j = table.index(&next);
+            entries[j].fetch_min(d, Ordering::Relaxed);
+            for k in table.symmetric_indexes(j) {
+                entries[k].fetch_min(d, Ordering::Relaxed);
+            }
+            deepest.fetch_max(d, Ordering::Relaxed);
+        }
+    }
+
+    found
+}
+
+/// Every table the solver knows about.
+pub const TABLES: [&Table; 11] = [
+    &CORNERS,
+    &EDGES1,
+    &EDGES2,
+    &EDGES7A,
+    &EDGES7B,
+    &EDGES8A,
+    &EDGES8B,
+    &EDGES1_ADDITIVE,
+    &EDGES2_ADDITIVE,
+    &CORNERS_SYM,
+    &EDGES_UD,
+];
+
+lazy_static! {
+    static ref CORNERS_REDUCTION: SymmetryReduction = SymmetryReduction::new(CORNERS.spec);
+    static ref EDGES_UD_REDUCTION: SymmetryReduction = SymmetryReduction::new(EDGES_UD.spec);
+}
+
+/// The corners like `CORNERS`, with one entry for every cube symmetric to each other, about 40
+/// times fewer.
+pub const CORNERS_SYM: Table = Table {
+    name: "corners-sym",
+    file_name: "corners-sym.pdb",
+    compressed_file_name: "corners-sym.data.gz",
+    spec: CORNERS.spec,
+    cost: Cost::Moves,
+    symmetry: Some(|| &CORNERS_REDUCTION),
+};
+
+/// The eight edges of the U and D layers, which the 16 symmetries keeping the UD axis keep there,
+/// so it takes a sixteenth of the memory of `EDGES8A`.
+pub const EDGES_UD: Table = Table {
+    name: "edges-ud",
+    file_name: "edges-ud.pdb",
+    compressed_file_name: "edges-ud.data.gz",
+    spec: PatternSpec::new(0, 0xf0f),
+    cost: Cost::Moves,
+    symmetry: Some(|| &EDGES_UD_REDUCTION),
+};
+
+/// The table called `name`.
+pub fn table(name: &str) -> Option<&'static Table> {
+    TABLES.iter().find(|t| t.name == name).copied()
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    /// The table file couldn't be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// The table data couldn't be decoded.
+    Corrupt { table: &'static str, reason: String },
+    /// The table decoded, but its length doesn't match its number of entries under any packing.
+    WrongSize {
+        table: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// The table file holds a different table.
+    WrongTable { table: &'static str, found: String },
+    /// The table can't be stored with the requested packing.
+    Unpackable {
+        table: &'static str,
+        packing: Packing,
+        reason: String,
+    },
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::Io { path, source } => {
+                write!(f, "couldn't read {}: {}", path.display(), source)
+            }
+            TableError::Corrupt { table, reason } => {
+                write!(f, "{} table is corrupt: {}", table, reason)
+            }
+            TableError::WrongSize {
+                table,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} table has {} entries, expected {}",
+                table, found, expected
+            ),
+            TableError::WrongTable { table, found } => {
+                write!(f, "expected the {} table, found {}", table, found)
+            }
+            TableError::Unpackable {
+                table,
+                packing,
+                reason,
+            } => write!(f, "can't pack {} table as {}: {}", table, packing, reason),
+        }
+    }
+}
+
+impl error::Error for TableError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TableError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The distance to solved of every pattern of one `Table`.
+pub struct PatternDatabase {
+    table: &'static Table,
+    entries: PackedTable,
+}
+
+impl PatternDatabase {
+    /// Wraps generated table data, checking it has the right number of entries.
+    pub fn new(table: &'static Table, data: Vec<u8>) -> Result<Self, TableError> {
+        if data.len() != table.size() {
+            return Err(TableError::WrongSize {
+                table: table.name,
+                expected: table.size(),
+                found: data.len(),
+            });
+        }
+
+        Ok(PatternDatabase {
+            table,
+            entries: PackedTable::unpacked(data),
+        })
+    }
+
+    /// The same table stored with `packing`. A mod 3 table can't be repacked any other way, as
+    /// it doesn't hold the distances themselves.
+    pub fn pack(&self, packing: Packing) -> Result<Self, TableError> {
+        let unpackable = |reason: String| TableError::Unpackable {
+            table: self.table.name,
+            packing,
+            reason,
+        };
+
+        let from = self.entries.packing();
+        if from == packing {
+            return Ok(PatternDatabase {
+                table: self.table,
+                entries: self.entries.clone(),
+            });
+        }
+        if from == Packing::Mod3 {
+            return Err(unpackable(
+                "the table only holds distances mod 3".to_string(),
+            ));
+        }
+
+        let distances: Vec<u8> = (0..self.entries.len())
+            .map(|i| self.entries.get(i))
+            .collect();
+        let entries = PackedTable::pack(&distances, packing)
+            .map_err(|d| unpackable(format!("distance {} doesn't fit", d)))?;
+
+        PatternDatabase::with_entries(self.table, entries)
+    }
+
+    // mod 3 entries are read back by walking down to solved one move at a time, which needs
+    // every move to cost one
+    fn with_entries(table: &'static Table, entries: PackedTable) -> Result<Self, TableError> {
+        if entries.packing() == Packing::Mod3 && table.cost != Cost::Moves {
+            return Err(TableError::Unpackable {
+                table: table.name,
+                packing: Packing::Mod3,
+                reason: "its moves don't all cost one".to_string(),
+            });
+        }
+
+        Ok(PatternDatabase { table, entries })
+    }
+
+    /// Loads `table.file_name` from `dir`, or `table.compressed_file_name` if there isn't one.
+    pub fn load<P: AsRef<Path>>(table: &'static Table, dir: P) -> Result<Self, TableError> {
+        let dir = dir.as_ref();
+
+        match PatternDatabase::open(table, dir.join(table.file_name)) {
+            Err(TableError::Io { path, source }) if source.kind() == io::ErrorKind::NotFound => {
+                let compressed = dir.join(table.compressed_file_name);
+                if compressed.exists() {
+                    PatternDatabase::open(table, compressed)
+                } else {
+                    Err(TableError::Io { path, source })
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Reads a table from the gzipped, bincode encoded bytes of a table file, e.g. one embedded
+    /// with `include_bytes!`.
+    pub fn from_bytes(table: &'static Table, bytes: &[u8]) -> Result<Self, TableError> {
+        PatternDatabase::from_reader(table, bytes)
+    }
+
+    /// Opens the table file at `path`. Files in the `table_file` format are mapped, anything
+    /// else is read into memory: gzipped bincode if it ends in `.gz`, plain bincode otherwise.
+    pub fn open<P: AsRef<Path>>(table: &'static Table, path: P) -> Result<Self, TableError> {
+        let path = path.as_ref();
+        let io_error = |source| io_error(path, source);
+        let mut file = File::open(path).map_err(io_error)?;
+
+        if is_gzipped(path) {
+            return PatternDatabase::from_reader(table, file);
+        }
+
+        let mut magic = Vec::new();
+        (&mut file)
+            .take(table_file::MAGIC.len() as u64)
+            .read_to_end(&mut magic)
+            .map_err(io_error)?;
+        file.seek(SeekFrom::Start(0)).map_err(io_error)?;
+
+        if table_file::is_table_file(&magic) {
+            PatternDatabase::map(table, &file, path)
+        } else {
+            PatternDatabase::decode(table, io::BufReader::new(file))
+        }
+    }
+
+    fn map(table: &'static Table, file: &File, path: &Path) -> Result<Self, TableError> {
+        // Safety: the map is only ever read. Like any mapped file it would change under us if
+        // someone wrote to the table file while it's in use, which `save` doesn't do: it renames
+        // a new file over the old one, which stays as it was for as long as it's mapped.
+        let map = unsafe { Mmap::map(file) }.map_err(|source| TableError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let corrupt = |reason: String| TableError::Corrupt {
+            table: table.name,
+            reason,
+        };
+
+        let header = table_file::read_header(&map).map_err(corrupt)?;
+        if header.id != table_file::table_id(table.name) {
+            return Err(TableError::WrongTable {
+                table: table.name,
+                found: header.id,
+            });
+        }
+        if header.entries != table.size() {
+            return Err(TableError::WrongSize {
+                table: table.name,
+                expected: table.size(),
+                found: header.entries,
+            });
+        }
+
+        let data = &map[table_file::HEADER_LEN..];
+        if header.data_len != header.packing.bytes_for(header.entries)
+            || data.len() != header.data_len
+        {
+            return Err(corrupt(format!(
+                "expected {} bytes of entries, found {}",
+                header.packing.bytes_for(header.entries),
+                data.len()
+            )));
+        }
+        if table_file::checksum(data) != header.checksum {
+            return Err(corrupt("checksum mismatch".to_string()));
+        }
+
+        let entries = PackedTable::mapped(
+            Arc::new(map),
+            table_file::HEADER_LEN,
+            header.packing,
+            header.entries,
+        );
+
+        PatternDatabase::with_entries(table, entries)
+    }
+
+    fn from_reader<R: Read>(table: &'static Table, reader: R) -> Result<Self, TableError> {
+        PatternDatabase::decode(table, GzDecoder::new(reader))
+    }
+
+    fn decode<R: Read>(table: &'static Table, reader: R) -> Result<Self, TableError> {
+        let data: Vec<u8> = bincode::deserialize_from(reader).map_err(|e| TableError::Corrupt {
+            table: table.name,
+            reason: e.to_string(),
+        })?;
+
+        let found = data.len();
+        let entries = PackedTable::from_raw(data, table.size()).ok_or(TableError::WrongSize {
+            table: table.name,
+            expected: table.size(),
+            found,
+        })?;
+
+        PatternDatabase::with_entries(table, entries)
+    }
+
+    /// Writes the table to `path`: as gzipped bincode if it ends in `.gz`, in the `table_file`
+    /// format otherwise.
+    ///
+    /// The table is written next to `path` and then renamed over it, so processes that have the
+    /// old file mapped keep reading the old file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TableError> {
+        let path = path.as_ref();
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp = path.with_file_name(tmp_name);
+
+        let result = self
+            .write(&tmp, is_gzipped(path))
+            .and_then(|()| fs::rename(&tmp, path).map_err(|source| io_error(path, source)));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn write(&self, path: &Path, gzipped: bool) -> Result<(), TableError> {
+        let file = BufWriter::new(File::create(path).map_err(|e| io_error(path, e))?);
+
+        if !gzipped {
+            return table_file::write(
+                file,
+                &table_file::table_id(self.table.name),
+                self.entries.packing(),
+                self.entries.len(),
+                self.entries.bytes(),
+            )
+            .map_err(|e| io_error(path, e));
+        }
+
+        let mut writer = GzEncoder::new(file, Compression::best());
+        bincode::serialize_into(&mut writer, self.entries.bytes()).map_err(|e| {
+            TableError::Corrupt {
+                table: self.table.name,
+                reason: e.to_string(),
+            }
+        })?;
+        writer
+            .finish()
+            .and_then(|mut w| w.flush())
+            .map_err(|e| io_error(path, e))
+    }
+
+    pub fn table(&self) -> &'static Table {
+        self.table
+    }
+
+    pub fn entries(&self) -> &PackedTable {
+        &self.entries
+    }
+
+    /// How many entries there are at each distance from solved, or each distance mod 3 for a
+    /// mod 3 table.
+    pub fn histogram(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        for i in 0..self.entries.len() {
+            let d = self.entries.get(i) as usize;
+            if counts.len() <= d {
+                counts.resize(d + 1, 0);
+            }
+            counts[d] += 1;
+        }
+
+        counts
+    }
+
+    /// Whether the entry at `index` agrees with its neighbors: one move changes the distance by
+    /// at most what it costs, and unless it's the solved pattern some move gets closer by that
+    /// much. For a mod 3 table only the last part can be checked.
+    pub fn is_consistent(&self, index: usize) -> bool {
+        let d = self.entries.get(index);
+        if self.table.index(&CubieCube::solved()) == index {
+            return d == 0;
+        }
+
+        let c = self.table.from_index(index);
+        let mod3 = self.entries.packing() == Packing::Mod3;
+        if mod3 && d > 2 {
+            return false;
+        }
+
+        let mut closer = false;
+        for &mv in Move::ALL.iter() {
+            let mut next = c;
+            next.apply(mv);
+            let n = self.entries.get(self.table.index(&next));
+
+            if mod3 {
+                closer |= n == (d + 2) % 3;
+            } else {
+                let (d, n) = (d as u16, n as u16);
+                let cost = self.table.move_cost(&c, &next) as u16;
+                if n + cost < d || d + cost < n {
+                    return false;
+                }
+                closer |= cost > 0 && n + cost == d;
+            }
+        }
+
+        closer
+    }
+
+    /// A lower bound on the number of moves needed to solve `c`, or on what solving it costs if
+    /// moves don't all cost one.
+    ///
+    /// A mod 3 table only knows the distance up to a multiple of 3, but each move changes it by
+    /// at most one, so it is found by following the table down to solved, one lookup per move
+    /// for each step.
+    pub fn distance(&self, c: &CubieCube) -> u8 {
+        let index = self.table.index(c);
+        if self.entries.packing() != Packing::Mod3 {
+            return self.entries.get(index);
+        }
+
+        let solved = self.table.index(&CubieCube::solved());
+        let mut curr = *c;
+        let mut index = index;
+        let mut distance = 0;
+        while index != solved {
+            let closer = (self.entries.get(index) + 2) % 3;
+            let (next, next_index) = Move::ALL
+                .iter()
+                .map(|&mv| {
+                    let mut next = curr;
+                    next.apply(mv);
+                    (next, self.table.index(&next))
+                })
+                .find(|&(_, i)| self.entries.get(i) == closer)
+                .expect("mod 3 table should lead to solved");
+
+            curr = next;
+            index = next_index;
+            distance += 1;
+        }
+
+        distance
+    }
+}
+
+fn is_gzipped(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
+
+// the gzipped table compiled in for `table`, if its feature is on
+fn embedded_bytes(table: &Table) -> Option<&'static [u8]> {
+    match table.name {
+        #[cfg(feature = "embedded-corners")]
+        "corners" => Some(EMBEDDED_CORNERS),
+        #[cfg(feature = "embedded-edges1")]
+        "edges1" => Some(EMBEDDED_EDGES1),
+        #[cfg(feature = "embedded-edges2")]
+        "edges2" => Some(EMBEDDED_EDGES2),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "embedded-corners")]
+const EMBEDDED_CORNERS: &[u8] = include_bytes!("../tables/corners.data.gz");
+#[cfg(feature = "embedded-corners")]
+const _: () = assert!(
+    !is_lfs_pointer(EMBEDDED_CORNERS),
+    "tables/corners.data.gz is a Git LFS pointer, run `git lfs pull` to embed it"
+);
+
+#[cfg(feature = "embedded-edges1")]
+const EMBEDDED_EDGES1: &[u8] = include_bytes!("../tables/edges1.data.gz");
+#[cfg(feature = "embedded-edges1")]
+const _: () = assert!(
+    !is_lfs_pointer(EMBEDDED_EDGES1),
+    "tables/edges1.data.gz is a Git LFS pointer, run `git lfs pull` to embed it"
+);
+
+#[cfg(feature = "embedded-edges2")]
+const EMBEDDED_EDGES2: &[u8] = include_bytes!("../tables/edges2.data.gz");
+#[cfg(feature = "embedded-edges2")]
+const _: () = assert!(
+    !is_lfs_pointer(EMBEDDED_EDGES2),
+    "tables/edges2.data.gz is a Git LFS pointer, run `git lfs pull` to embed it"
+);
+
+// whether `bytes` are the small text file Git LFS leaves in place of a file it hasn't fetched
+#[cfg(any(
+    feature = "embedded-corners",
+    feature = "embedded-edges1",
+    feature = "embedded-edges2"
+))]
+const fn is_lfs_pointer(bytes: &[u8]) -> bool {
+    let prefix = b"version https://git-lfs";
+    if bytes.len() < prefix.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+fn io_error(path: &Path, source: io::Error) -> TableError {
+    TableError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// The heuristic used by `search::ida_star`: the largest distance given by the corners table, the
+/// two edges tables and any extra tables, like the larger edge groups, or by the additive tables
+/// together. The same goes for the cubes as far from solved that it's told to look up as well.
+pub struct Heuristic {
+    pub corners: PatternDatabase,
+    pub edges1: PatternDatabase,
+    pub edges2: PatternDatabase,
+    pub extra: Vec<PatternDatabase>,
+    /// Tables counting the pieces moved, tracking disjoint pieces, whose distances are added up.
+    pub additive: Vec<PatternDatabase>,
+    /// Whether to look up the inverse of the cube too, which the tables see differently.
+    pub inverse: bool,
+    /// Symmetries, by position in `SYMMETRIES`, whose conjugates of the cube are looked up too.
+    /// Only those mapping a table's pieces onto other pieces tell it anything new, see
+    /// `symmetry::moving`.
+    pub symmetries: Vec<usize>,
+}
+
+impl Heuristic {
+    /// Loads the tables from `dir`.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, TableError> {
+        let dir = dir.as_ref();
+
+        Ok(Heuristic {
+            corners: PatternDatabase::load(&CORNERS, dir)?,
+            edges1: PatternDatabase::load(&EDGES1, dir)?,
+            edges2: PatternDatabase::load(&EDGES2, dir)?,
+            extra: Vec::new(),
+            additive: Vec::new(),
+            inverse: false,
+            symmetries: Vec::new(),
+        })
+    }
+
+    /// Loads the tables from `dir`, along with `extra`.
+    ///
+    /// Panics if an extra table counts pieces moved rather than moves, like the additive ones,
+    /// since its distances would then overestimate; see `load_additive` for those.
+    pub fn load_with<P: AsRef<Path>>(dir: P, extra: &[&'static Table]) -> Result<Self, TableError> {
+        for table in extra {
+            assert_eq!(table.cost, Cost::Moves, "{} is additive", table.name);
+        }
+
+        let dir = dir.as_ref();
+        let mut heuristic = Heuristic::load(dir)?;
+        for &table in extra {
+            heuristic.extra.push(PatternDatabase::load(table, dir)?);
+        }
+
+        Ok(heuristic)
+    }
+
+    /// Loads the tables from `dir`, along with the `additive` tables to be added up.
+    ///
+    /// Adding up only helps where the tables share out the cost of each move well, and splitting
+    /// the edges doesn't: every move moves four edges, about half of them from each group, so
+    /// `EDGES1_ADDITIVE` and `EDGES2_ADDITIVE` add up to about the average of what the two groups
+    /// need rather than the larger. On random cubes their sum beats the larger of `EDGES1` and
+    /// `EDGES2` about one time in eight, so it isn't worth the extra lookups, and nothing loads
+    /// them by default.
+    ///
+    /// Panics if an additive table counts moves rather than pieces moved, or if two of them track
+    /// the same piece, since adding them up could then overestimate.
+    pub fn load_additive<P: AsRef<Path>>(
+        dir: P,
+        additive: &[&'static Table],
+    ) -> Result<Self, TableError> {
+        let mut corners = 0;
+        let mut edges = 0;
+        for table in additive {
+            assert_eq!(
+                table.cost,
+                Cost::TrackedPieces,
+                "{} isn't additive",
+                table.name
+            );
+            assert!(
+                corners & table.spec.corners == 0 && edges & table.spec.edges == 0,
+                "{} tracks pieces another additive table does",
+                table.name
+            );
+            corners |= table.spec.corners;
+            edges |= table.spec.edges;
+        }
+
+        let dir = dir.as_ref();
+        let mut heuristic = Heuristic::load(dir)?;
+        for &table in additive {
+            heuristic.additive.push(PatternDatabase::load(table, dir)?);
+        }
+
+        Ok(heuristic)
+    }
+
+    /// Loads the tables from `tables_dir()`.
+    pub fn from_env() -> Result<Self, TableError> {
+        Heuristic::load(tables_dir())
+    }
+
+    /// The tables compiled into the binary with the `embedded-corners`, `embedded-edges1` and
+    /// `embedded-edges2` features, and the others loaded from `tables_dir()`.
+    pub fn embedded() -> Result<Self, TableError> {
+        let dir = tables_dir();
+        let load = |table: &'static Table| match embedded_bytes(table) {
+            Some(bytes) => PatternDatabase::from_bytes(table, bytes),
+            None => PatternDatabase::load(table, &dir),
+        };
+
+        Ok(Heuristic {
+            corners: load(&CORNERS)?,
+            edges1: load(&EDGES1)?,
+            edges2: load(&EDGES2)?,
+            extra: Vec::new(),
+            additive: Vec::new(),
+            inverse: false,
+            symmetries: Vec::new(),
+        })
+    }
+
+    pub fn h(&self, c: &CubieCube) -> u8 {
+        let mut h = self.lookup(c);
+        if self.inverse {
+            h = cmp::max(h, self.lookup(&c.inverse()));
+        }
+        for &s in &self.symmetries {
+            h = cmp::max(h, self.lookup(&SYMMETRIES[s].conjugate(c)));
+        }
+
+        h
+    }
+
+    // the largest distance the tables give `c` itself
+    fn lookup(&self, c: &CubieCube) -> u8 {
+        let h1 = self.corners.distance(c);
+        let h2 = self.edges1.distance(c);
+        let h3 = self.edges2.distance(c);
+        let h = cmp::max(h1, cmp::max(h2, h3));
+        let h = self.extra.iter().map(|db| db.distance(c)).fold(h, cmp::max);
+
+        cmp::max(h, self.additive_distance(c))
+    }
+
+    /// The additive tables' lower bound on the number of moves needed to solve `c`. Each move
+    /// moves four corners and four edges, so the pieces moved, added up over the tables, are at
+    /// most four times the number of moves for each kind of piece tracked.
+    pub fn additive_distance(&self, c: &CubieCube) -> u8 {
+        let specs = self.additive.iter().map(|db| db.table().spec);
+        let corners = specs.clone().any(|spec| spec.corners != 0);
+        let edges = specs.clone().any(|spec| spec.edges != 0);
+        let per_move = 4 * (corners as usize + edges as usize);
+        if per_move == 0 {
+            return 0;
+        }
+
+        let moved: usize = self.additive.iter().map(|db| db.distance(c) as usize).sum();
+        moved.div_ceil(per_move) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cubie::CubieCube;
+    use crate::moves::Algorithm;
+    use crate::packed::Packing;
+    use lazy_static::lazy_static;
+
+    use crate::pattern::PatternSpec;
+    use crate::pdb::{
+        generate, table, Cost, Heuristic, PatternDatabase, Table, TableError, CORNERS,
+        EDGES1_ADDITIVE, EDGES7A, EDGES7B, EDGES8A, EDGES8B,
+    };
+    use crate::symmetry::SymmetryReduction;
+
+    #[test]
+    fn test_missing_tables() {
+        match Heuristic::load("no/such/directory") {
+            Err(TableError::Io { path, .. }) => {
+                assert_eq!(path, std::path::Path::new("no/such/directory/corners.pdb"))
+            }
+            _ => panic!("expected an io error"),
+        }
+    }
+
+    #[test]
+    fn test_corrupt_table() {
+        match PatternDatabase::from_bytes(&CORNERS, b"not a table") {
+            Err(TableError::Corrupt { table, .. }) => assert_eq!(table, "corners"),
+            _ => panic!("expected a corrupt table"),
+        }
+
+        match PatternDatabase::new(&CORNERS, vec![0; 10]) {
+            Err(TableError::WrongSize {
+                expected, found, ..
+            }) => assert_eq!((expected, found), (88179840, 10)),
+            _ => panic!("expected a size mismatch"),
+        }
+    }
+
+    // corner permutations only, small enough to generate in a test
+    const CORNER_PERMUTATION: Table = Table {
+        name: "corner-permutation",
+        file_name: "corner-permutation.pdb",
+        compressed_file_name: "corner-permutation.data.gz",
+        spec: PatternSpec::new(0xff, 0).permutation_only(),
+        cost: Cost::Moves,
+        symmetry: None,
+    };
+
+    #[test]
+    fn test_generated_table() {
+        let mut levels = Vec::new();
+        let data = generate(&CORNER_PERMUTATION, 3, |depth, filled| {
+            levels.push((depth, filled))
+        });
+        assert_eq!(levels.last(), Some(&(7, 40320)));
+        assert_eq!(generate(&CORNER_PERMUTATION, 1, |_, _| {}), data);
+
+        let db = PatternDatabase::new(&CORNER_PERMUTATION, data).unwrap();
+
+        assert_eq!(db.histogram().iter().sum::<usize>(), 40320);
+        assert_eq!(db.histogram()[0], 1);
+        assert!((0..40320).all(|i| db.is_consistent(i)));
+
+        let dir = std::env::temp_dir();
+        for name in &["cube-solver-test.pdb", "cube-solver-test.data.gz"] {
+            let path = dir.join(name);
+            db.save(&path).unwrap();
+            let read = PatternDatabase::open(&CORNER_PERMUTATION, &path).unwrap();
+            assert_eq!(read.entries(), db.entries());
+            assert_eq!(read.entries().is_mapped(), name.ends_with(".pdb"));
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        let mut broken: Vec<u8> = (0..40320).map(|i| db.entries().get(i)).collect();
+        broken[100] += 2;
+        let broken = PatternDatabase::new(&CORNER_PERMUTATION, broken).unwrap();
+        assert!(!broken.is_consistent(100));
+
+        // saving over a table in use leaves the one in use as it was
+        let path = dir.join("cube-solver-test-replaced.pdb");
+        db.save(&path).unwrap();
+        let mapped = PatternDatabase::open(&CORNER_PERMUTATION, &path).unwrap();
+        broken.save(&path).unwrap();
+        assert_eq!(mapped.entries(), db.entries());
+        let read = PatternDatabase::open(&CORNER_PERMUTATION, &path).unwrap();
+        assert_eq!(read.entries(), broken.entries());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_packed_distances() {
+        let data = generate(&CORNER_PERMUTATION, 2, |_, _| {});
+        let db = PatternDatabase::new(&CORNER_PERMUTATION, data).unwrap();
+
+        for &packing in &[Packing::Nibble, Packing::Mod3] {
+            let packed = db.pack(packing).unwrap();
+            assert!((0..40320).step_by(13).all(|i| packed.is_consistent(i)));
+
+            let mut c = CubieCube::solved();
+            for mv in "R U' F2 D L' B2 U R2".parse::<Algorithm>().unwrap().iter() {
+                c.apply(*mv);
+                assert_eq!(packed.distance(&c), db.distance(&c));
+            }
+
+            let path = std::env::temp_dir().join(format!("cube-solver-{}.pdb", packing));
+            packed.save(&path).unwrap();
+            let read = PatternDatabase::open(&CORNER_PERMUTATION, &path).unwrap();
+            assert_eq!(read.entries(), packed.entries());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        let mod3 = db.pack(Packing::Mod3).unwrap();
+        match mod3.pack(Packing::Nibble) {
+            Err(TableError::Unpackable { packing, .. }) => assert_eq!(packing, Packing::Nibble),
+            _ => panic!("expected mod 3 to be unpackable"),
+        }
+    }
+
+    #[test]
+    fn test_damaged_table_file() {
+        let data = generate(&CORNER_PERMUTATION, 1, |_, _| {});
+        let db = PatternDatabase::new(&CORNER_PERMUTATION, data).unwrap();
+        let path = std::env::temp_dir().join("cube-solver-damaged.pdb");
+        db.save(&path).unwrap();
+
+        match PatternDatabase::open(&CORNERS, &path) {
+            Err(TableError::WrongTable { table, found }) => {
+                assert_eq!(
+                    (table, found.as_str()),
+                    ("corners", "3x3x3/corner-permutation")
+                )
+            }
+            _ => panic!("expected the wrong table"),
+        }
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[1000] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        match PatternDatabase::open(&CORNER_PERMUTATION, &path) {
+            Err(TableError::Corrupt { reason, .. }) => assert_eq!(reason, "checksum mismatch"),
+            _ => panic!("expected a checksum mismatch"),
+        }
+
+        std::fs::write(&path, &bytes[..1000]).unwrap();
+        match PatternDatabase::open(&CORNER_PERMUTATION, &path) {
+            Err(TableError::Corrupt { .. }) => {}
+            _ => panic!("expected a truncated table"),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    lazy_static! {
+        static ref CORNER_PERMUTATION_REDUCTION: SymmetryReduction =
+            SymmetryReduction::new(CORNER_PERMUTATION.spec);
+    }
+
+    // the same, with one entry for symmetric cubes
+    const CORNER_PERMUTATION_SYM: Table = Table {
+        name: "corner-permutation-sym",
+        symmetry: Some(|| &CORNER_PERMUTATION_REDUCTION),
+        ..CORNER_PERMUTATION
+    };
+
+    #[test]
+    fn test_symmetric_table() {
+        let full = generate(&CORNER_PERMUTATION, 1, |_, _| {});
+        let full = PatternDatabase::new(&CORNER_PERMUTATION, full).unwrap();
+        let reduced = generate(&CORNER_PERMUTATION_SYM, 2, |_, _| {});
+        assert!(reduced.len() * 40 < full.entries().len());
+        let reduced = PatternDatabase::new(&CORNER_PERMUTATION_SYM, reduced).unwrap();
+        assert!((0..reduced.entries().len()).all(|i| reduced.is_consistent(i)));
+
+        for i in 0..full.entries().len() {
+            let c = CORNER_PERMUTATION.from_index(i);
+            assert_eq!(reduced.distance(&c), full.entries().get(i));
+            assert_eq!(full.distance(&c.inverse()), full.entries().get(i));
+        }
+    }
+
+    // the same, with each move costing the four corners it moves
+    const CORNER_PERMUTATION_MOVED: Table = Table {
+        cost: Cost::TrackedPieces,
+        symmetry: None,
+        ..CORNER_PERMUTATION
+    };
+
+    // two halves of the corners, to be added up
+    const FRONT_CORNERS: Table = Table {
+        name: "front-corners",
+        file_name: "front-corners.pdb",
+        compressed_file_name: "front-corners.data.gz",
+        spec: PatternSpec::new(0x0f, 0).permutation_only(),
+        cost: Cost::TrackedPieces,
+        symmetry: None,
+    };
+
+    const BACK_CORNERS: Table = Table {
+        name: "back-corners",
+        spec: PatternSpec::new(0xf0, 0).permutation_only(),
+        ..FRONT_CORNERS
+    };
+
+    #[test]
+    fn test_additive_tables() {
+        let moves = generate(&CORNER_PERMUTATION, 1, |_, _| {});
+        let moved = generate(&CORNER_PERMUTATION_MOVED, 2, |_, _| {});
+        assert!(moves.iter().zip(&moved).all(|(&d, &m)| m == 4 * d));
+
+        let front = generate(&FRONT_CORNERS, 3, |_, _| {});
+        assert_eq!(generate(&FRONT_CORNERS, 1, |_, _| {}), front);
+        let front = PatternDatabase::new(&FRONT_CORNERS, front).unwrap();
+        let back = generate(&BACK_CORNERS, 2, |_, _| {});
+        let back = PatternDatabase::new(&BACK_CORNERS, back).unwrap();
+        assert!((0..front.entries().len()).all(|i| front.is_consistent(i)));
+
+        // never more than the actual number of moves, for every corner permutation
+        for (i, &d) in moves.iter().enumerate() {
+            let c = CORNER_PERMUTATION.from_index(i);
+            let sum = (front.distance(&c) + back.distance(&c)) as usize;
+            assert!(sum.div_ceil(4) <= d as usize, "index {}", i);
+        }
+
+        match front.pack(Packing::Mod3) {
+            Err(TableError::Unpackable { packing, .. }) => assert_eq!(packing, Packing::Mod3),
+            _ => panic!("expected mod 3 to be unpackable"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "edges1-additive is additive")]
+    fn test_extra_tables_count_moves() {
+        let _ = Heuristic::load_with("no/such/directory", &[&EDGES1_ADDITIVE]);
+    }
+
+    #[test]
+    fn test_edge_group_tables() {
+        for t in &[&EDGES7A, &EDGES7B, &EDGES8A, &EDGES8B] {
+            let mut c = CubieCube::solved();
+            for mv in "D' F2 L U' B R2 D2 F' U L2"
+                .parse::<Algorithm>()
+                .unwrap()
+                .iter()
+            {
+                c.apply(*mv);
+                let i = t.index(&c);
+                assert!(i < t.size());
+                assert_eq!(t.index(&t.from_index(i)), i);
+            }
+            assert_eq!(t.index(&t.from_index(t.size() - 1)), t.size() - 1);
+        }
+    }
+
+    #[test]
+    fn test_table_names() {
+        assert_eq!(table("edges2").map(|t| t.size()), Some(42577920));
+        assert!(table("edges3").is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! Ranking of permutations, i.e. numbering them densely so they can index a table.
+//!
+//! Partial permutations place `k` distinct pieces among `n` slots and leave the rest blank, as
+//! when a pattern database only tracks some of the edges. Ranks follow lexicographic order with
+//! blank sorting after every piece, which is the order the permutation tables used to be listed
+//! in, so tables generated against those stay valid.
+
+/// Marks a slot holding none of the tracked pieces.
+pub const BLANK: u8 = u8::MAX;
+
+const FACTORIAL: [usize; 13] = [
+    1, 1, 2, 6, 24, 120, 720, 5040, 40320, 362880, 3628800, 39916800, 479001600,
+];
+
+/// Number of ways to place `k` distinct pieces in `n` slots.
+pub fn partial_count(n: usize, k: usize) -> usize {
+    FACTORIAL[n] / FACTORIAL[n - k]
+}
+
+/// Number of ways to choose `k` of `n` slots.
+pub fn combination_count(n: usize, k: usize) -> usize {
+    FACTORIAL[n] / (FACTORIAL[k] * FACTORIAL[n - k])
+}
+
+/// Rank of a permutation of `0..perm.len()`, in `0..perm.len()!`.
+pub fn rank_permutation(perm: &[u8]) -> usize {
+    rank_partial(perm)
+}
+
+/// Writes the permutation of `0..out.len()` with the given rank to `out`.
+pub fn unrank_permutation(rank: usize, out: &mut [u8]) {
+    let n = out.len();
+    unrank_partial(rank, n, out);
+}
+
+/// Rank of a partial permutation, where `slots` holds pieces `0..k` once each and `BLANK`
+/// everywhere else. The result is in `0..partial_count(slots.len(), k)`.
+pub fn rank_partial(slots: &[u8]) -> usize {
+    let n = slots.len();
+    let mut blanks = slots.iter().filter(|&&x| x == BLANK).count();
+    let mut used = 0u32;
+    let mut rank = 0;
+
+    for (i, &x) in slots.iter().enumerate() {
+        let remaining = n - i - 1;
+        // arrangements of the rest of the slots once this one is filled with a piece
+        let block = FACTORIAL[remaining] / FACTORIAL[blanks];
+
+        let smaller = if x == BLANK {
+            blanks -= 1;
+            // every piece not placed yet sorts before blank
+            (n - i) - (blanks + 1)
+        } else {
+            let below = (1u32 << x) - 1;
+            used |= 1 << x;
+            (below & !used).count_ones() as usize
+        };
+
+        rank += smaller * block;
+    }
+
+    rank
+}
+
+/// Writes the partial permutation of `k` pieces with the given rank to `out`, with `BLANK` in the
+/// slots not holding a piece.
+pub fn unrank_partial(mut rank: usize, k: usize, out: &mut [u8]) {
+    let n = out.len();
+    let mut blanks = n - k;
+    let mut used = 0u32;
+
+    for (i, slot) in out.iter_mut().enumerate() {
+        let remaining = n - i - 1;
+
+        let mut chosen = BLANK;
+        if n - i > blanks {
+            let block = FACTORIAL[remaining] / FACTORIAL[blanks];
+            let mut skip = rank / block;
+            if skip < (n - i) - blanks {
+                rank %= block;
+                for piece in 0..k as u8 {
+                    if used & (1 << piece) == 0 {
+                        if skip == 0 {
+                            chosen = piece;
+                            break;
+                        }
+                        skip -= 1;
+                    }
+                }
+            } else {
+                rank -= ((n - i) - blanks) * block;
+            }
+        }
+
+        if chosen == BLANK {
+            blanks -= 1;
+        } else {
+            used |= 1 << chosen;
+        }
+        *slot = chosen;
+    }
+}
+
+/// Rank of a choice of slots, where `slots` holds a piece in the chosen slots and `BLANK` in the
+/// others, and the pieces aren't told apart. Ranks follow the same order as `rank_partial`, and
+/// are in `0..combination_count(slots.len(), k)` for `k` chosen slots.
+pub fn rank_combination(slots: &[u8]) -> usize {
+    let n = slots.len();
+    let mut pieces = slots.iter().filter(|&&x| x != BLANK).count();
+    let mut rank = 0;
+
+    for (i, &x) in slots.iter().enumerate() {
+        if x == BLANK {
+            // every choice with a piece here comes first
+            if pieces > 0 {
+                rank += combination_count(n - i - 1, pieces - 1);
+            }
+        } else {
+            pieces -= 1;
+        }
+    }
+
+    rank
+}
+
+/// Writes the choice of `k` slots with the given rank to `out`, with 0 in the chosen slots and
+/// `BLANK` in the others.
+pub fn unrank_combination(mut rank: usize, k: usize, out: &mut [u8]) {
+    let n = out.len();
+    let mut pieces = k;
+
+    for (i, slot) in out.iter_mut().enumerate() {
+        *slot = BLANK;
+        if pieces > 0 {
+            let block = combination_count(n - i - 1, pieces - 1);
+            if rank < block {
+                *slot = 0;
+                pieces -= 1;
+            } else {
+                rank -= block;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rank::{
+        combination_count, partial_count, rank_combination, rank_partial, rank_permutation,
+        unrank_combination, unrank_partial, unrank_permutation, BLANK,
+    };
+
+    #[test]
+    fn test_permutation_lexicographic() {
+        assert_eq!(rank_permutation(&[0, 1, 2, 3]), 0);
+        assert_eq!(rank_permutation(&[0, 1, 3, 2]), 1);
+        assert_eq!(rank_permutation(&[3, 2, 1, 0]), 23);
+        assert_eq!(rank_permutation(&[7, 6, 5, 4, 3, 2, 1, 0]), 40319);
+
+        let mut perm = [0; 8];
+        for i in 0..40320 {
+            unrank_permutation(i, &mut perm);
+            assert_eq!(rank_permutation(&perm), i);
+        }
+    }
+
+    #[test]
+    fn test_partial_lexicographic() {
+        // 2 pieces in 3 slots, in order: 01_, 0_1, 10_, 1_0, _01, _10
+        let expected = [
+            [0, 1, BLANK],
+            [0, BLANK, 1],
+            [1, 0, BLANK],
+            [1, BLANK, 0],
+            [BLANK, 0, 1],
+            [BLANK, 1, 0],
+        ];
+        assert_eq!(partial_count(3, 2), expected.len());
+
+        for (i, slots) in expected.iter().enumerate() {
+            assert_eq!(rank_partial(slots), i);
+
+            let mut out = [0; 3];
+            unrank_partial(i, 2, &mut out);
+            assert_eq!(&out, slots);
+        }
+    }
+
+    #[test]
+    fn test_partial_roundtrip() {
+        let mut slots = [0; 12];
+        for i in (0..partial_count(12, 6)).step_by(97) {
+            unrank_partial(i, 6, &mut slots);
+            assert_eq!(slots.iter().filter(|&&x| x == BLANK).count(), 6);
+            assert_eq!(rank_partial(&slots), i);
+        }
+    }
+
+    #[test]
+    fn test_combination() {
+        // 2 of 4 slots, in order: XX__, X_X_, X__X, _XX_, _X_X, __XX
+        assert_eq!(rank_combination(&[0, 0, BLANK, BLANK]), 0);
+        assert_eq!(rank_combination(&[0, BLANK, BLANK, 0]), 2);
+        assert_eq!(rank_combination(&[BLANK, BLANK, 0, 0]), 5);
+
+        let mut slots = [0; 12];
+        for i in 0..combination_count(12, 4) {
+            unrank_combination(i, 4, &mut slots);
+            assert_eq!(slots.iter().filter(|&&x| x != BLANK).count(), 4);
+            assert_eq!(rank_combination(&slots), i);
+        }
+    }
+}
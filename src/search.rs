@@ -1,68 +1,41 @@
-use std::cmp;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::fs::File;
-use std::path::Path;
-use std::u8;
+use std::error;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use flate2::read::GzDecoder;
-use lazy_static::lazy_static;
+use crate::cube::{Cube, ValidationError};
+use crate::cubie::CubieCube;
+use crate::limits::{Limits, Progress, StopReason, Tracker};
+use crate::moves::{Algorithm, Move};
+use crate::pdb::{Heuristic, PatternDatabase};
+use crate::thistlethwaite;
+use crate::two_phase::{self, Phase1, Phase2, PHASE2_MOVES};
 
-use crate::cube;
-use crate::cube::Cube;
-
-lazy_static! {
-    static ref CORNERS_TABLE: Vec<u8> = load_corners_table();
-    static ref EDGES1_TABLE: Vec<u8> = load_edges1_table();
-    static ref EDGES2_TABLE: Vec<u8> = load_edges2_table();
-}
-
-fn load_corners_table() -> Vec<u8> {
-    let path = Path::new("tables/corners.data.gz");
-    let file = File::open(&path).unwrap();
-    let d = GzDecoder::new(file);
-
-    bincode::deserialize_from(d).unwrap()
+struct SearchNode {
+    state: Cube,
+    moves: Algorithm,
 }
 
-fn load_edges1_table() -> Vec<u8> {
-    let path = Path::new("tables/edges1.data.gz");
-    let file = File::open(&path).unwrap();
-    let d = GzDecoder::new(file);
-
-    bincode::deserialize_from(d).unwrap()
-}
-
-fn load_edges2_table() -> Vec<u8> {
-    let path = Path::new("tables/edges2.data.gz");
-    let file = File::open(&path).unwrap();
-    let d = GzDecoder::new(file);
-
-    bincode::deserialize_from(d).unwrap()
-}
-
-struct SearchNode<'a> {
-    state: Cube<'a>,
-    moves: String,
-}
-
-struct SearchNodeSmall<'a> {
-    state: Cube<'a>,
+struct SearchNodeSmall {
+    state: CubieCube,
     distance: u8,
 }
 
-impl<'a> SearchNode<'a> {
-    fn neighbors(&self) -> VecDeque<SearchNode<'a>> {
+impl SearchNode {
+    fn neighbors(&self) -> VecDeque<SearchNode> {
         let mut result = VecDeque::new();
-        let all_moves = vec![
-            "U", "U'", "U2", "F", "F'", "F2", "R", "R'", "R2", "D", "D'", "D2", "B", "B'", "B2",
-            "L", "L'", "L2",
-        ];
 
-        for m in all_moves.iter() {
-            let mut state = self.state.clone();
-            state.twist(m);
+        for m in Move::ALL.iter() {
+            let mut state = self.state;
+            state.apply(*m);
 
-            let moves = format!("{} {}", self.moves, m);
+            let mut moves = self.moves.clone();
+            moves.push(*m);
             result.push_back(SearchNode { state, moves });
         }
 
@@ -70,40 +43,52 @@ impl<'a> SearchNode<'a> {
     }
 }
 
-impl<'a> SearchNodeSmall<'a> {
-    fn neighbors(&self) -> VecDeque<(SearchNodeSmall<'a>, String)> {
+impl SearchNodeSmall {
+    fn neighbors(&self) -> VecDeque<(SearchNodeSmall, Move)> {
         let mut result = VecDeque::new();
-        let all_moves = vec![
-            "U", "U'", "U2", "F", "F'", "F2", "R", "R'", "R2", "D", "D'", "D2", "B", "B'", "B2",
-            "L", "L'", "L2",
-        ];
 
-        for m in all_moves.into_iter() {
-            let mut state = self.state.clone();
-            state.twist(m);
+        for m in Move::ALL.iter() {
+            let mut state = self.state;
+            state.apply(*m);
 
             let distance = self.distance + 1;
-            result.push_back((SearchNodeSmall { state, distance }, m.to_string()));
+            result.push_back((SearchNodeSmall { state, distance }, *m));
         }
 
         result
     }
 }
 
-pub fn bfs(start: Cube) -> Option<String> {
+/// Finds a shortest solution breadth first, or `None` if there is none, as for a cube put
+/// together wrong. Progress is reported a depth at a time.
+pub fn bfs(start: Cube, limits: &mut Limits) -> Result<Option<Algorithm>, SearchError> {
+    if start.is_solved() {
+        return Ok(Some(Algorithm::new()));
+    }
+
+    let mut tracker = Tracker::new(limits);
     let mut queue = VecDeque::new();
     let mut seen = HashSet::new();
     queue.push_back(SearchNode {
         state: start,
-        moves: String::new(),
+        moves: Algorithm::new(),
     });
     while let Some(curr) = queue.pop_front() {
-        if curr.state.is_solved() {
-            return Some(curr.moves);
+        let depth = curr.moves.len() as u8;
+        if depth > tracker.bound() {
+            tracker.finish_iteration();
+            tracker.start_iteration(depth);
         }
 
+        tracker.expand()?;
         let mut neighbors = curr.neighbors();
         while let Some(neighbor) = neighbors.pop_front() {
+            // stop as soon as the solved state is generated rather than a whole level later
+            if neighbor.state.is_solved() {
+                tracker.finish_iteration();
+                return Ok(Some(neighbor.moves));
+            }
+
             let state_string = neighbor.state.state_string();
             if !seen.contains(&state_string) {
                 queue.push_back(neighbor);
@@ -112,270 +97,1123 @@ pub fn bfs(start: Cube) -> Option<String> {
         }
     }
 
-    None
+    Ok(None)
 }
 
-pub fn solve_exact<F: Fn(&Cube) -> String>(c: Cube, state_string: F) -> HashMap<String, String> {
+/// Finds a shortest way to reach every state `state_string` tells apart from `c`, by the string
+/// it gives, breadth first. Progress is reported a depth at a time.
+pub fn solve_exact<F: Fn(&Cube) -> String>(
+    c: Cube,
+    state_string: F,
+    limits: &mut Limits,
+) -> Result<HashMap<String, Algorithm>, SearchError> {
+    let mut tracker = Tracker::new(limits);
     let mut queue = VecDeque::new();
     let mut solution_table = HashMap::new();
     queue.push_back(SearchNode {
         state: c,
-        moves: String::new(),
+        moves: Algorithm::new(),
     });
     while let Some(curr) = queue.pop_front() {
-        let neighbors = curr.neighbors();
-        for neighbor in neighbors.into_iter() {
-            let state_string = state_string(&neighbor.state);
-            if !solution_table.contains_key(&state_string) {
-                solution_table.insert(state_string, neighbor.moves.clone());
-                queue.push_back(neighbor);
-                println!("{}", solution_table.len());
-            }
+        let depth = curr.moves.len() as u8;
+        if depth > tracker.bound() {
+            tracker.finish_iteration();
+            tracker.start_iteration(depth);
         }
-    }
-
-    solution_table
-}
 
-pub fn gen_table<F: Fn(&Cube) -> usize>(c: Cube, result_size: usize, index_fn: F) -> Vec<u8> {
-    let solved_index = index_fn(&c);
-    let mut solution_table = vec![0 as u8; result_size];
-    let mut queue = VecDeque::new();
-    let mut counter = 0 as usize;
-
-    queue.push_back(SearchNodeSmall {
-        state: c,
-        distance: 0,
-    });
-
-    while let Some(curr) = queue.pop_front() {
+        tracker.expand()?;
         let neighbors = curr.neighbors();
-        for (neighbor, _) in neighbors.into_iter() {
-            if solution_table[index_fn(&neighbor.state)] == 0 {
-                solution_table[index_fn(&neighbor.state)] = curr.distance + 1;
+        for neighbor in neighbors.into_iter() {
+            if let Entry::Vacant(e) = solution_table.entry(state_string(&neighbor.state)) {
+                e.insert(neighbor.moves.clone());
                 queue.push_back(neighbor);
-                println!(
-                    "states visited: {}, distance from solved: {}",
-                    counter, curr.distance
-                );
-                counter += 1;
             }
         }
     }
+    tracker.finish_iteration();
 
-    // reset the index of solved state to 0
-    solution_table[solved_index] = 0;
-
-    solution_table
+    Ok(solution_table)
 }
 
-pub fn solve_corners(c: &Cube) -> String {
+// follows the table downhill to a cube it considers solved
+fn solve_table(
+    c: &Cube,
+    table: &PatternDatabase,
+    limits: &mut Limits,
+) -> Result<Algorithm, SearchError> {
+    let mut tracker = Tracker::new(limits);
+    let c = c.to_cubies()?;
     let mut curr = SearchNodeSmall {
-        state: c.clone(),
-        distance: CORNERS_TABLE[cube::corners_index(c)],
+        state: c,
+        distance: table.distance(&c),
     };
-    dbg!();
 
-    let mut sol = String::new();
-    while CORNERS_TABLE[cube::corners_index(&curr.state)] != 0 {
+    let mut sol = Algorithm::new();
+    while table.distance(&curr.state) != 0 {
+        tracker.expand()?;
         let neighbors = curr.neighbors();
         let mut min_distance = 100;
-        let mut next_move = String::new();
+        let mut next_move = None;
 
         for (neighbor, neighbor_move) in neighbors.into_iter() {
-            let neighbor_distance = CORNERS_TABLE[cube::corners_index(&neighbor.state)];
+            let neighbor_distance = table.distance(&neighbor.state);
             if neighbor_distance < min_distance {
                 min_distance = neighbor_distance;
-                next_move = neighbor_move;
+                next_move = Some(neighbor_move);
                 curr = neighbor;
             }
         }
 
-        sol.push_str(&next_move);
-        sol.push_str(" ");
+        sol.push(next_move.unwrap());
     }
 
-    sol.trim().to_string()
+    Ok(sol)
 }
 
-pub fn solve_edges1(c: &Cube) -> String {
-    let mut curr = SearchNodeSmall {
-        state: c.clone(),
-        distance: EDGES1_TABLE[cube::edges1_index(c)],
-    };
-    dbg!();
+pub fn solve_corners(
+    c: &Cube,
+    heuristic: &Heuristic,
+    limits: &mut Limits,
+) -> Result<Algorithm, SearchError> {
+    solve_table(c, &heuristic.corners, limits)
+}
 
-    let mut sol = String::new();
-    while EDGES1_TABLE[cube::edges1_index(&curr.state)] != 0 {
-        let neighbors = curr.neighbors();
-        let mut min_distance = 100;
-        let mut next_move = String::new();
+pub fn solve_edges1(
+    c: &Cube,
+    heuristic: &Heuristic,
+    limits: &mut Limits,
+) -> Result<Algorithm, SearchError> {
+    solve_table(c, &heuristic.edges1, limits)
+}
 
-        for (neighbor, neighbor_move) in neighbors.into_iter() {
-            let neighbor_distance = EDGES1_TABLE[cube::edges1_index(&neighbor.state)];
-            if neighbor_distance < min_distance {
-                min_distance = neighbor_distance;
-                next_move = neighbor_move;
-                curr = neighbor;
+pub fn solve_edges2(
+    c: &Cube,
+    heuristic: &Heuristic,
+    limits: &mut Limits,
+) -> Result<Algorithm, SearchError> {
+    solve_table(c, &heuristic.edges2, limits)
+}
+
+enum SearchResult {
+    Found,
+    NewBound(u8),
+}
+
+/// A solution found by `ida_star`, which is optimal, or by `two_phase`, along with how much work
+/// it took to find.
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub moves: Algorithm,
+    pub length: usize,
+    /// Nodes whose successors were generated, over all iterations.
+    pub nodes_expanded: u64,
+    /// Number of depth-first passes, one per bound tried.
+    pub iterations: u32,
+    pub elapsed: Duration,
+}
+
+/// A solution found by `thistlethwaite`, phase by phase.
+#[derive(Clone, Debug)]
+pub struct PhasedSolution {
+    /// The moves of each of `thistlethwaite::PHASES`.
+    pub phases: Vec<Algorithm>,
+    pub moves: Algorithm,
+    pub length: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchError {
+    InvalidCube(ValidationError),
+    /// No solution of at most `max_depth` moves exists.
+    DepthExceeded {
+        max_depth: u8,
+    },
+    /// The search was stopped by its `Limits` before it was done, having got as far as
+    /// `progress`.
+    Stopped {
+        reason: StopReason,
+        progress: Progress,
+    },
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::InvalidCube(e) => write!(f, "cube can't be solved: {}", e),
+            SearchError::DepthExceeded { max_depth } => {
+                write!(f, "no solution within {} moves", max_depth)
             }
+            SearchError::Stopped { reason, progress } => write!(
+                f,
+                "search stopped, {}, at bound {} after {} nodes",
+                reason, progress.bound, progress.nodes_expanded
+            ),
         }
+    }
+}
 
-        sol.push_str(&next_move);
-        sol.push_str(" ");
+impl error::Error for SearchError {}
+
+impl From<ValidationError> for SearchError {
+    fn from(e: ValidationError) -> Self {
+        SearchError::InvalidCube(e)
     }
+}
 
-    sol.trim().to_string()
+/// Finds an optimal solution of at most `max_depth` moves. Every cube can be solved in 20.
+///
+/// Progress is reported after each bound. When stopped, the bound it got to is a lower bound on
+/// the length of the optimal solutions.
+pub fn ida_star(
+    root: Cube,
+    heuristic: &Heuristic,
+    max_depth: u8,
+    limits: &mut Limits,
+) -> Result<Solution, SearchError> {
+    let root = root.to_cubies()?;
+    let mut search = IDAStar::new(heuristic, root, Tracker::new(limits));
+    let mut bound = heuristic.h(&root);
+    let mut iterations = 0;
+    loop {
+        if bound > max_depth {
+            return Err(SearchError::DepthExceeded { max_depth });
+        }
+
+        iterations += 1;
+        search.tracker.start_iteration(bound);
+        let t = search.search(bound)?;
+        search.tracker.finish_iteration();
+        if let SearchResult::Found = t {
+            let moves = search.moves();
+            return Ok(Solution {
+                length: moves.len(),
+                moves,
+                nodes_expanded: search.tracker.nodes_expanded(),
+                iterations,
+                elapsed: search.tracker.elapsed(),
+            });
+        }
+        if let SearchResult::NewBound(b) = t {
+            bound = b;
+        }
+    }
 }
 
-pub fn solve_edges2(c: &Cube) -> String {
-    let mut curr = SearchNodeSmall {
-        state: c.clone(),
-        distance: EDGES2_TABLE[cube::edges2_index(c)],
-    };
+// moves below the root at which `parallel_ida_star` hands subtrees to its workers, giving them
+// a few thousand to share
+const SPLIT_DEPTH: u8 = 3;
 
-    let mut sol = String::new();
-    while EDGES2_TABLE[cube::edges2_index(&curr.state)] != 0 {
-        let neighbors = curr.neighbors();
-        let mut min_distance = 100;
-        let mut next_move = String::new();
+/// Like `ida_star`, but searching each bound on `threads` threads, which take the subtrees below
+/// the first few moves one at a time. Once one finds a solution the others stop, so the solution
+/// is as short as the one `ida_star` finds, though it may not be the same one.
+pub fn parallel_ida_star(
+    root: Cube,
+    heuristic: &Heuristic,
+    max_depth: u8,
+    threads: usize,
+    limits: &mut Limits,
+) -> Result<Solution, SearchError> {
+    let root = root.to_cubies()?;
+    let mut search = IDAStar::new(heuristic, root, Tracker::new(limits));
+    let mut bound = heuristic.h(&root);
+    let mut iterations = 0;
+    loop {
+        if bound > max_depth {
+            return Err(SearchError::DepthExceeded { max_depth });
+        }
 
-        for (neighbor, neighbor_move) in neighbors.into_iter() {
-            let neighbor_distance = EDGES2_TABLE[cube::edges2_index(&neighbor.state)];
-            if neighbor_distance < min_distance {
-                min_distance = neighbor_distance;
-                next_move = neighbor_move;
-                curr = neighbor;
+        iterations += 1;
+        search.tracker.start_iteration(bound);
+        let mut frontier = Vec::new();
+        let split = search.split(bound, SPLIT_DEPTH.min(bound), &mut frontier)?;
+        let t = match split {
+            SearchResult::Found => SearchResult::Found,
+            SearchResult::NewBound(b) => {
+                match search_frontier(
+                    &mut search.tracker,
+                    heuristic,
+                    root,
+                    &frontier,
+                    bound,
+                    threads,
+                )? {
+                    Ok(path) => {
+                        search.path = path;
+                        SearchResult::Found
+                    }
+                    Err(frontier_bound) => SearchResult::NewBound(b.min(frontier_bound)),
+                }
             }
+        };
+        search.tracker.finish_iteration();
+
+        match t {
+            SearchResult::Found => {
+                let moves = search.moves();
+                return Ok(Solution {
+                    length: moves.len(),
+                    moves,
+                    nodes_expanded: search.tracker.nodes_expanded(),
+                    iterations,
+                    elapsed: search.tracker.elapsed(),
+                });
+            }
+            SearchResult::NewBound(b) => bound = b,
         }
+    }
+}
+
+// searches below each path from `root` in `frontier` on `threads` threads, giving the path to a
+// solution or else the smallest f cost over `bound`
+fn search_frontier(
+    tracker: &mut Tracker,
+    heuristic: &Heuristic,
+    root: CubieCube,
+    frontier: &[Vec<Move>],
+    bound: u8,
+    threads: usize,
+) -> Result<Result<Vec<Move>, u8>, SearchError> {
+    let stop = tracker.stop_token();
+    let nodes = Arc::new(AtomicU64::new(tracker.nodes_expanded()));
+    let next = AtomicUsize::new(0);
+    let min_bound = AtomicU8::new(u8::MAX);
+    let solution = Mutex::new(None);
+    let stopped = Mutex::new(None);
+
+    let workers: Vec<Limits> = (0..threads.max(1))
+        .map(|_| tracker.worker(&stop, &nodes))
+        .collect();
+    thread::scope(|s| {
+        for mut limits in workers {
+            let (stop, next, min_bound) = (&stop, &next, &min_bound);
+            let (solution, stopped) = (&solution, &stopped);
+            s.spawn(move || {
+                let mut search = IDAStar::new(heuristic, root, Tracker::new(&mut limits));
+                search.tracker.start_iteration(bound);
+
+                while let Some(path) = frontier.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    search.start_at(root, path);
+                    match search.search(bound) {
+                        Ok(SearchResult::NewBound(b)) => {
+                            min_bound.fetch_min(b, Ordering::Relaxed);
+                            continue;
+                        }
+                        Ok(SearchResult::Found) => {
+                            solution.lock().unwrap().get_or_insert(search.path.clone());
+                        }
+                        // the first to stop says why, the rest were stopped by it
+                        Err(SearchError::Stopped { reason, .. }) => {
+                            stopped.lock().unwrap().get_or_insert(reason);
+                        }
+                        Err(e) => unreachable!("{}", e),
+                    }
+                    stop.cancel();
+                    break;
+                }
+
+                search.tracker.flush();
+            });
+        }
+    });
 
-        sol.push_str(&next_move);
-        sol.push_str(" ");
+    tracker.add_nodes(nodes.load(Ordering::Relaxed) - tracker.nodes_expanded());
+    if let Some(path) = solution.into_inner().unwrap() {
+        Ok(Ok(path))
+    } else if let Some(reason) = stopped.into_inner().unwrap() {
+        Err(tracker.stopped(reason))
+    } else {
+        Ok(Err(min_bound.into_inner()))
     }
+}
 
-    sol.trim().to_string()
+/// What `ida_star_all` found, the solutions themselves having gone to its callback.
+#[derive(Clone, Debug)]
+pub struct Solutions {
+    /// Length of the optimal solutions.
+    pub optimal_length: usize,
+    pub count: usize,
+    pub nodes_expanded: u64,
+    pub iterations: u32,
+    pub elapsed: Duration,
 }
 
-enum SearchResult {
-    Found,
-    NewBound(u8),
+/// Calls `found` with every solution of at most `extra` moves more than the optimal ones, and of
+/// at most `max_depth` moves, shortest first, until it returns `ControlFlow::Break`.
+///
+/// Only canonical sequences are searched, see `Move::can_follow`, so solutions differing just in
+/// the order of turns of opposite faces are found once. Nor are solutions passing through solved
+/// on the way, which are shorter ones with moves that cancel out tacked on. If `limits` stop the
+/// search, the solutions found by then have already been passed on.
+pub fn ida_star_all<F: FnMut(&Algorithm) -> ControlFlow<()>>(
+    root: Cube,
+    heuristic: &Heuristic,
+    max_depth: u8,
+    extra: u8,
+    limits: &mut Limits,
+    mut found: F,
+) -> Result<Solutions, SearchError> {
+    let root = root.to_cubies()?;
+    let mut search = IDAStar::new(heuristic, root, Tracker::new(limits));
+    let mut bound = heuristic.h(&root);
+    let mut iterations = 0;
+    let mut optimal_length = None;
+    loop {
+        let last = optimal_length.map_or(max_depth, |l: u8| l.saturating_add(extra).min(max_depth));
+        if bound > last {
+            break;
+        }
+
+        iterations += 1;
+        search.tracker.start_iteration(bound);
+        let t = search.enumerate(bound, &mut found)?;
+        search.tracker.finish_iteration();
+        if optimal_length.is_none() && search.solutions > 0 {
+            optimal_length = Some(bound);
+        }
+        match t {
+            SearchResult::Found => break,
+            // once there are solutions every longer bound has to be tried
+            SearchResult::NewBound(b) => {
+                bound = if optimal_length.is_some() {
+                    bound + 1
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    match optimal_length {
+        Some(length) => Ok(Solutions {
+            optimal_length: length as usize,
+            count: search.solutions,
+            nodes_expanded: search.tracker.nodes_expanded(),
+            iterations,
+            elapsed: search.tracker.elapsed(),
+        }),
+        None => Err(SearchError::DepthExceeded { max_depth }),
+    }
 }
 
-pub struct IDAStarNode<'a> {
-    pub state: Cube<'a>,
-    pub mv_to_get_here: String,
+// what the depth-first search does at a node within the bound
+enum Visit {
+    Expand,
+    Skip,
+    Stop,
 }
 
-impl<'a> PartialEq for IDAStarNode<'a> {
-    fn eq(&self, other: &Self) -> bool {
-        self.state == other.state
+// a depth-first search keeping one cube, which moves are applied to and undone on the way down
+// and back up, and a stack of move indices, so nodes cost no allocations
+struct IDAStar<'h, 'l, 'a> {
+    heuristic: &'h Heuristic,
+    cube: CubieCube,
+    // the moves from the root to `cube`
+    path: Vec<Move>,
+    // for each node on the path being expanded, the index in `Move::ALL` of the next move to try
+    next: Vec<u8>,
+    tracker: Tracker<'l, 'a>,
+    solutions: usize,
+}
+
+impl<'h, 'l, 'a> IDAStar<'h, 'l, 'a> {
+    fn new(heuristic: &'h Heuristic, root: CubieCube, tracker: Tracker<'l, 'a>) -> Self {
+        IDAStar {
+            heuristic,
+            cube: root,
+            path: Vec::new(),
+            next: Vec::new(),
+            tracker,
+            solutions: 0,
+        }
+    }
+
+    fn moves(&self) -> Algorithm {
+        self.path.iter().copied().collect()
+    }
+
+    // starts from `root` with `path` already played
+    fn start_at(&mut self, root: CubieCube, path: &[Move]) {
+        self.cube = root;
+        self.path.clear();
+        for &mv in path {
+            self.cube.apply(mv);
+            self.path.push(mv);
+        }
+    }
+
+    fn push(&mut self, mv: Move) {
+        self.cube.apply(mv);
+        self.path.push(mv);
+    }
+
+    fn pop(&mut self) {
+        let mv = self.path.pop().unwrap();
+        self.cube.apply(mv.inverse());
+    }
+
+    // searches below the current path, passing each node with an f cost within `bound` to
+    // `visit`, and leaves the path where it stopped if `visit` says to, or else as it was
+    fn dfs<F: FnMut(&Self) -> Visit>(
+        &mut self,
+        bound: u8,
+        mut visit: F,
+    ) -> Result<SearchResult, SearchError> {
+        let base = self.path.len();
+        self.path.reserve(bound as usize);
+        self.next.clear();
+        self.next.reserve(bound as usize + 1);
+        let mut min = u8::MAX;
+
+        let mut entered = true;
+        loop {
+            if entered {
+                let f = self.path.len() as u8 + self.heuristic.h(&self.cube);
+                let expand = if f > bound {
+                    min = min.min(f);
+                    false
+                } else {
+                    match visit(self) {
+                        Visit::Expand => {
+                            self.tracker.expand()?;
+                            true
+                        }
+                        Visit::Skip => false,
+                        Visit::Stop => return Ok(SearchResult::Found),
+                    }
+                };
+
+                if expand {
+                    self.next.push(0);
+                } else if self.path.len() == base {
+                    break;
+                } else {
+                    self.pop();
+                }
+            }
+
+            let last = self.path.last().copied();
+            let next = self.next.last_mut().unwrap();
+            let mv = Move::ALL[*next as usize..]
+                .iter()
+                .find(|mv| last.is_none_or(|last| mv.can_follow(last)));
+            match mv {
+                Some(&mv) => {
+                    *next = mv.index() as u8 + 1;
+                    self.push(mv);
+                    entered = true;
+                }
+                None => {
+                    self.next.pop();
+                    if self.path.len() == base {
+                        break;
+                    }
+                    self.pop();
+                    entered = false;
+                }
+            }
+        }
+
+        Ok(SearchResult::NewBound(min))
+    }
+
+    // passes each solution of exactly `bound` moves on to `found`, returning `Found` if it asks
+    // to stop
+    fn enumerate<F: FnMut(&Algorithm) -> ControlFlow<()>>(
+        &mut self,
+        bound: u8,
+        found: &mut F,
+    ) -> Result<SearchResult, SearchError> {
+        let mut solutions = 0;
+        let t = self.dfs(bound, |s| {
+            if !s.cube.is_solved() {
+                Visit::Expand
+            } else if s.path.len() as u8 == bound {
+                solutions += 1;
+                match found(&s.moves()) {
+                    ControlFlow::Break(()) => Visit::Stop,
+                    ControlFlow::Continue(()) => Visit::Skip,
+                }
+            } else {
+                // shorter solutions were found with a smaller bound
+                Visit::Skip
+            }
+        });
+        self.solutions += solutions;
+        t
+    }
+
+    // collects the paths `depth` moves down that might lead to a solution within `bound` in
+    // `frontier`, unless there's a solution on the way
+    fn split(
+        &mut self,
+        bound: u8,
+        depth: u8,
+        frontier: &mut Vec<Vec<Move>>,
+    ) -> Result<SearchResult, SearchError> {
+        self.dfs(bound, |s| {
+            if s.cube.is_solved() {
+                Visit::Stop
+            } else if s.path.len() as u8 == depth {
+                frontier.push(s.path.clone());
+                Visit::Skip
+            } else {
+                Visit::Expand
+            }
+        })
+    }
+
+    fn search(&mut self, bound: u8) -> Result<SearchResult, SearchError> {
+        self.dfs(bound, |s| {
+            if s.cube.is_solved() {
+                Visit::Stop
+            } else {
+                Visit::Expand
+            }
+        })
     }
 }
 
-fn h(c: &Cube) -> u8 {
-    let h1 = CORNERS_TABLE[cube::corners_index(c)];
-    let h2 = EDGES1_TABLE[cube::edges1_index(c)];
-    let h3 = EDGES2_TABLE[cube::edges2_index(c)];
+/// Finds a solution of at most `max_len` moves with Kociemba's two-phase algorithm, giving up
+/// after `timeout` or when `limits` say so.
+///
+/// Phase 1 searches for ever longer ways into the subgroup G1 = <U, D, R2, L2, F2, B2>, and for
+/// each phase 2 searches for the shortest way on to solved within G1 that keeps the whole solution
+/// within `max_len`. The first solution found is returned. Solutions are rarely optimal, but one
+/// of at most 21 moves usually takes milliseconds. `DepthExceeded` means none was found.
+/// Progress is reported after each phase 1 depth.
+pub fn two_phase(
+    c: &Cube,
+    max_len: u8,
+    timeout: Duration,
+    limits: &mut Limits,
+) -> Result<Solution, SearchError> {
+    let root = c.to_cubies()?;
+    let mut tracker = Tracker::new(limits);
+    tracker.stop_by(Instant::now() + timeout);
+    let mut search = TwoPhase {
+        root,
+        max_len,
+        tracker,
+        moves: Vec::new(),
+    };
+
+    let phase1 = Phase1::new(&root);
+    for (tried, depth) in (phase1.h()..=max_len).enumerate() {
+        search.tracker.start_iteration(depth);
+        let found = search.phase1(phase1, depth)?;
+        search.tracker.finish_iteration();
+        if found {
+            let moves = Algorithm::from(search.moves);
+            return Ok(Solution {
+                length: moves.len(),
+                moves,
+                nodes_expanded: search.tracker.nodes_expanded(),
+                iterations: tried as u32 + 1,
+                elapsed: search.tracker.elapsed(),
+            });
+        }
+    }
 
-    cmp::max(h1, cmp::max(h2, h3))
+    Err(SearchError::DepthExceeded { max_depth: max_len })
 }
 
-pub fn ida_star(root: Cube) -> String {
-    let mut bound = h(&root);
-    let mut path = vec![IDAStarNode {
-        state: root,
-        mv_to_get_here: String::new(),
-    }];
-    loop {
-        let t = search(&mut path, 0, bound);
-        if let SearchResult::Found = t {
-            let mut solution = String::new();
-            for node in path.iter() {
-                solution.push_str(&node.mv_to_get_here);
-                solution.push(' ');
+struct TwoPhase<'l, 'a> {
+    root: CubieCube,
+    max_len: u8,
+    tracker: Tracker<'l, 'a>,
+    // the moves of both phases so far
+    moves: Vec<Move>,
+}
+
+impl TwoPhase<'_, '_> {
+    fn follows_last(&self, mv: Move) -> bool {
+        self.moves.last().is_none_or(|&prev| mv.can_follow(prev))
+    }
+
+    // searches for ways into G1 of exactly `togo` more moves, going on with phase 2 from each
+    fn phase1(&mut self, c: Phase1, togo: u8) -> Result<bool, SearchError> {
+        if togo == 0 {
+            // one ending in a move of G1 was already tried a move shorter
+            let last_leaves_g1 = self
+                .moves
+                .last()
+                .is_none_or(|&mv| !two_phase::is_phase2_move(mv));
+            return if c.is_solved() && last_leaves_g1 {
+                self.start_phase2()
+            } else {
+                Ok(false)
+            };
+        }
+        if c.h() > togo {
+            return Ok(false);
+        }
+
+        self.tracker.expand()?;
+        for &mv in Move::ALL.iter() {
+            if self.follows_last(mv) {
+                self.moves.push(mv);
+                if self.phase1(c.apply(mv), togo - 1)? {
+                    return Ok(true);
+                }
+                self.moves.pop();
             }
-            solution.trim();
-            return solution;
         }
-        if let SearchResult::NewBound(b) = t {
-            bound = b;
+
+        Ok(false)
+    }
+
+    fn start_phase2(&mut self) -> Result<bool, SearchError> {
+        let mut c = self.root;
+        for &mv in &self.moves {
+            c.apply(mv);
         }
+        let c = Phase2::new(&c);
+
+        let limit = self.max_len - self.moves.len() as u8;
+        for depth in c.h()..=limit {
+            if self.phase2(c, depth)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
     }
-}
 
-fn search<'a>(path: &mut Vec<IDAStarNode<'a>>, g: u8, bound: u8) -> SearchResult {
-    let node = path.last().unwrap();
-    let f = g + h(&node.state);
-    if f > bound {
-        return SearchResult::NewBound(f);
-    } else if node.state.is_solved() {
-        return SearchResult::Found;
-    }
-
-    let mut min = u8::MAX;
-    for succ in node.state.successors().into_iter() {
-        if !path.contains(&succ) {
-            path.push(succ);
-            let t = search(path, g + 1, bound);
-            if let SearchResult::Found = t {
-                return t;
-            } else if let SearchResult::NewBound(b) = t {
-                if b < min {
-                    min = b;
+    fn phase2(&mut self, c: Phase2, togo: u8) -> Result<bool, SearchError> {
+        if togo == 0 {
+            return Ok(c.is_solved());
+        }
+        if c.h() > togo {
+            return Ok(false);
+        }
+
+        self.tracker.expand()?;
+        for (m, &mv) in PHASE2_MOVES.iter().enumerate() {
+            if self.follows_last(mv) {
+                self.moves.push(mv);
+                if self.phase2(c.apply(m), togo - 1)? {
+                    return Ok(true);
                 }
+                self.moves.pop();
             }
-            path.pop();
         }
+
+        Ok(false)
+    }
+}
+
+/// Solves a cube with Thistlethwaite's algorithm, taking it through ever smaller subgroups to
+/// solved, see `thistlethwaite`.
+///
+/// Each phase takes as few moves as it can, but that makes for about 30 in all, half as many
+/// again as `two_phase`. It doesn't search, so it takes no time once the tables are built.
+pub fn thistlethwaite(c: &Cube, limits: &mut Limits) -> Result<PhasedSolution, SearchError> {
+    let mut tracker = Tracker::new(limits);
+    let mut c = c.to_cubies()?;
+
+    let mut phases = Vec::new();
+    for phase in thistlethwaite::PHASES.iter() {
+        let mut moves = Algorithm::new();
+        let mut distance = phase.distance(&c);
+        while distance > 0 {
+            tracker.expand()?;
+            // the tables are exact, so some move always gets a step closer
+            let (next, mv) = phase
+                .moves
+                .iter()
+                .map(|&mv| {
+                    let mut next = c;
+                    next.apply(mv);
+                    (next, mv)
+                })
+                .find(|(next, _)| phase.distance(next) < distance)
+                .expect("every cube in a phase's subgroup has a move closer to its target");
+
+            c = next;
+            distance -= 1;
+            moves.push(mv);
+        }
+        phases.push(moves);
     }
 
-    SearchResult::NewBound(min)
+    let moves: Algorithm = phases.iter().flat_map(|p| p.iter()).copied().collect();
+    Ok(PhasedSolution {
+        length: moves.len(),
+        moves,
+        phases,
+        elapsed: tracker.elapsed(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cube;
-    use crate::cube::Cube;
+    use std::ops::ControlFlow;
+    use std::time::Duration;
+
+    use crate::cube::{Cube, ValidationError};
+    use crate::limits::{CancellationToken, Limits, StopReason};
+    use crate::pdb::{Heuristic, PatternDatabase, CORNERS, EDGES1, EDGES2};
     use crate::search;
-    use crate::transformations;
+    use crate::search::SearchError;
+    use crate::thistlethwaite;
+
+    // admissible but uninformed, so plain iterative deepening; fine for short scrambles
+    fn zero_heuristic() -> Heuristic {
+        Heuristic {
+            corners: PatternDatabase::new(&CORNERS, vec![0; CORNERS.size()]).unwrap(),
+            edges1: PatternDatabase::new(&EDGES1, vec![0; EDGES1.size()]).unwrap(),
+            edges2: PatternDatabase::new(&EDGES2, vec![0; EDGES2.size()]).unwrap(),
+            extra: Vec::new(),
+            additive: Vec::new(),
+            inverse: false,
+            symmetries: Vec::new(),
+        }
+    }
 
     #[test]
-    fn it_works() {
-        let t = transformations::cube2();
-        let mut c = Cube::new(2, &t);
-        c.twist("B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B");
-        println!("{}", search::bfs(c).unwrap());
+    fn test_bfs() {
+        let mut c = Cube::new(2);
+        c.twist(&"R U F'".parse().unwrap());
+        let sol = search::bfs(c, &mut Limits::new()).unwrap().unwrap();
+        assert!(sol.len() <= 3);
+        c.twist(&sol);
+        assert!(c.is_solved());
     }
 
     #[test]
+    #[ignore = "needs the pattern databases in tables/ (git lfs pull)"]
     fn test_solve_corners() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B");
-        println!("{}", search::solve_corners(&c));
+        let h = Heuristic::from_env().unwrap();
+        let mut c = Cube::new(3);
+        c.twist(&"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap());
+        let sol = search::solve_corners(&c, &h, &mut Limits::new()).unwrap();
+        c.twist(&sol);
+        assert_eq!(CORNERS.index(&c.to_cubies().unwrap()), 0);
     }
 
     #[test]
+    #[ignore = "needs the pattern databases in tables/ (git lfs pull)"]
     fn test_solve_edges1() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B");
-        println!("{}", search::solve_edges1(&c));
+        let h = Heuristic::from_env().unwrap();
+        let mut c = Cube::new(3);
+        c.twist(&"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap());
+        let solved = EDGES1.index(&Cube::new(3).to_cubies().unwrap());
+        let sol = search::solve_edges1(&c, &h, &mut Limits::new()).unwrap();
+        c.twist(&sol);
+        assert_eq!(EDGES1.index(&c.to_cubies().unwrap()), solved);
     }
 
     #[test]
+    #[ignore = "needs the pattern databases in tables/ (git lfs pull)"]
     fn test_solve_edges2() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B U2 R' F' L' B2 D' F L2");
-        println!("{}", cube::edges2_index(&c));
-        println!("{}", search::solve_edges2(&c));
+        let h = Heuristic::from_env().unwrap();
+        let mut c = Cube::new(3);
+        c.twist(&"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B".parse().unwrap());
+        let solved = EDGES2.index(&Cube::new(3).to_cubies().unwrap());
+        let sol = search::solve_edges2(&c, &h, &mut Limits::new()).unwrap();
+        c.twist(&sol);
+        assert_eq!(EDGES2.index(&c.to_cubies().unwrap()), solved);
     }
 
     #[test]
+    fn test_solve_exact() {
+        // where the first corner is and how it's twisted
+        let corner = |c: &Cube| {
+            let c = c.to_cubies().unwrap();
+            let i = c.cp.iter().position(|&p| p == 0).unwrap();
+            format!("{} {}", i, c.co[i])
+        };
+
+        let mut depths = Vec::new();
+        let mut limits = Limits::new().on_progress(|p| depths.push(p.bound));
+        let table = search::solve_exact(Cube::new(3), corner, &mut limits).unwrap();
+        drop(limits);
+        assert_eq!(table.len(), 24);
+        assert!(depths.len() > 1 && depths.iter().enumerate().all(|(i, &d)| d as usize == i));
+
+        let mut limits = Limits::new().max_nodes(5);
+        assert!(matches!(
+            search::solve_exact(Cube::new(3), corner, &mut limits),
+            Err(SearchError::Stopped {
+                reason: StopReason::NodeBudget,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs the pattern databases in tables/ (git lfs pull)"]
     fn test_ida() {
-        let t = transformations::cube3();
-        let mut c = Cube::new(3, &t);
-        c.twist("B' R U L B U2 B' U' B' L B' F' U D B2 F'");
-        let sol = search::ida_star(c);
-        println!("{}", sol);
+        let h = Heuristic::from_env().unwrap();
+        let mut c = Cube::new(3);
+        c.twist(&"B' R U L B U2 B' U' B' L B' F' U D B2 F'".parse().unwrap());
+        let sol = search::ida_star(c, &h, 20, &mut Limits::new()).unwrap();
+        assert!(sol.length <= 16);
+        c.twist(&sol.moves);
+        assert!(c.is_solved());
+    }
+
+    #[test]
+    fn test_ida_short_scramble() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        let sol = search::ida_star(c, &h, 20, &mut Limits::new()).unwrap();
+        assert_eq!(sol.length, 3);
+        assert_eq!(sol.iterations, 4);
+        c.twist(&sol.moves);
+        assert!(c.is_solved());
+    }
+
+    #[test]
+    fn test_ida_max_depth() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        assert_eq!(
+            search::ida_star(c, &h, 2, &mut Limits::new()).unwrap_err(),
+            SearchError::DepthExceeded { max_depth: 2 }
+        );
+    }
+
+    #[test]
+    fn test_two_phase() {
+        for scramble in [
+            "B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B U2 R' F' L' B2 D' F L2",
+            "U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B",
+        ] {
+            let mut c = Cube::new(3);
+            c.twist(&scramble.parse().unwrap());
+
+            let sol =
+                search::two_phase(&c, 21, Duration::from_secs(60), &mut Limits::new()).unwrap();
+            assert!(sol.length <= 21);
+            c.twist(&sol.moves);
+            assert!(c.is_solved());
+        }
+
+        let solved = search::two_phase(
+            &Cube::new(3),
+            21,
+            Duration::from_secs(60),
+            &mut Limits::new(),
+        )
+        .unwrap();
+        assert_eq!(solved.length, 0);
+    }
+
+    #[test]
+    fn test_two_phase_limits() {
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+        assert_eq!(
+            search::two_phase(&c, 3, Duration::from_secs(60), &mut Limits::new())
+                .unwrap()
+                .length,
+            3
+        );
+        assert_eq!(
+            search::two_phase(&c, 2, Duration::from_secs(60), &mut Limits::new()).unwrap_err(),
+            SearchError::DepthExceeded { max_depth: 2 }
+        );
+
+        // far too short to find 16 moves for a 20 move scramble
+        let mut c = Cube::new(3);
+        c.twist(
+            &"U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2"
+                .parse()
+                .unwrap(),
+        );
+        assert!(matches!(
+            search::two_phase(&c, 16, Duration::from_millis(1), &mut Limits::new()),
+            Err(SearchError::Stopped {
+                reason: StopReason::Deadline,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_thistlethwaite() {
+        let mut c = Cube::new(3);
+        c.twist(
+            &"B' R D2 U2 R' L U D' F2 D' F2 L F2 L2 B U2 R' F' L' B2 D' F L2"
+                .parse()
+                .unwrap(),
+        );
+
+        let sol = search::thistlethwaite(&c, &mut Limits::new()).unwrap();
+        assert_eq!(sol.phases.len(), 4);
+        assert!(sol.length <= 7 + 10 + 13 + 15);
+        let mut cubies = c.to_cubies().unwrap();
+        for (phase, moves) in thistlethwaite::PHASES.iter().zip(sol.phases.iter()) {
+            assert!(moves.iter().all(|mv| phase.moves.contains(mv)));
+            cubies.twist(moves);
+            assert_eq!(phase.distance(&cubies), 0);
+        }
+
+        c.twist(&sol.moves);
+        assert!(c.is_solved());
+        assert_eq!(
+            search::thistlethwaite(&c, &mut Limits::new())
+                .unwrap()
+                .length,
+            0
+        );
+    }
+
+    #[test]
+    fn test_ida_all() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        let mut solutions = Vec::new();
+        let summary = search::ida_star_all(c, &h, 20, 1, &mut Limits::new(), |moves| {
+            solutions.push(moves.clone());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(summary.optimal_length, 3);
+        assert_eq!(summary.count, solutions.len());
+        assert_eq!(solutions[0].to_string(), "F U2 R'");
+        assert!(solutions[1..].iter().all(|s| s.len() == 4));
+        for s in &solutions {
+            let mut solved = c;
+            solved.twist(s);
+            assert!(solved.is_solved());
+        }
+
+        // U' D' and D' U' are one solution
+        let mut c = Cube::new(3);
+        c.twist(&"U D".parse().unwrap());
+        let summary = search::ida_star_all(c, &h, 20, 0, &mut Limits::new(), |_| {
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(summary.count, 1);
+    }
+
+    #[test]
+    fn test_ida_all_stops() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        let summary =
+            search::ida_star_all(c, &h, 20, 2, &mut Limits::new(), |_| ControlFlow::Break(()))
+                .unwrap();
+        assert_eq!(summary.count, 1);
+        assert_eq!(
+            search::ida_star_all(c, &h, 2, 2, &mut Limits::new(), |_| ControlFlow::Continue(
+                ()
+            ))
+            .unwrap_err(),
+            SearchError::DepthExceeded { max_depth: 2 }
+        );
+    }
+
+    #[test]
+    fn test_ida_limits() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        let mut bounds = Vec::new();
+        let mut limits = Limits::new().on_progress(|p| bounds.push((p.bound, p.iteration_nodes)));
+        search::ida_star(c, &h, 20, &mut limits).unwrap();
+        drop(limits);
+        assert_eq!(bounds.iter().map(|b| b.0).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(bounds[0].1, 1);
+        assert_eq!(bounds[1].1, 1 + 18);
+
+        // the first two bounds take 19 nodes, so a budget of 100 runs out at the third
+        let mut limits = Limits::new().max_nodes(100);
+        match search::ida_star(c, &h, 20, &mut limits) {
+            Err(SearchError::Stopped { reason, progress }) => {
+                assert_eq!(reason, StopReason::NodeBudget);
+                assert_eq!(progress.bound, 2);
+                assert_eq!(progress.nodes_expanded, 100);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let token = CancellationToken::new();
+        let mut limits = Limits::new().cancel_with(token.clone());
+        token.cancel();
+        for result in [
+            search::ida_star(c, &h, 20, &mut limits).map(|_| ()),
+            search::two_phase(&c, 21, Duration::from_secs(60), &mut limits).map(|_| ()),
+            search::thistlethwaite(&c, &mut limits).map(|_| ()),
+            search::bfs(c, &mut limits).map(|_| ()),
+        ] {
+            assert!(matches!(
+                result,
+                Err(SearchError::Stopped {
+                    reason: StopReason::Cancelled,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ida_nodes() {
+        let h = zero_heuristic();
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F'".parse().unwrap());
+
+        // every canonical sequence shorter than the bound is expanded: after a turn of U, F or
+        // R, 15 moves can follow, and after D, B or L, 12
+        let mut nodes = Vec::new();
+        let mut limits = Limits::new().on_progress(|p| nodes.push(p.iteration_nodes));
+        let sol = search::ida_star(c, &h, 20, &mut limits).unwrap();
+        drop(limits);
+        assert_eq!(nodes[..3], [1, 1 + 18, 1 + 18 + 9 * 15 + 9 * 12]);
+        c.twist(&sol.moves);
+        assert!(c.is_solved());
+    }
+
+    #[test]
+    fn test_parallel_ida() {
+        let h = zero_heuristic();
+        for (scramble, threads) in [("R U2 F' L", 3), ("U", 2), ("F2 B' D", 1)] {
+            let mut c = Cube::new(3);
+            c.twist(&scramble.parse().unwrap());
+
+            let sequential = search::ida_star(c, &h, 20, &mut Limits::new()).unwrap();
+            let sol = search::parallel_ida_star(c, &h, 20, threads, &mut Limits::new()).unwrap();
+            assert_eq!(sol.length, sequential.length);
+            assert_eq!(sol.iterations, sequential.iterations);
+            c.twist(&sol.moves);
+            assert!(c.is_solved());
+        }
+
+        let mut c = Cube::new(3);
+        c.twist(&"R U2 F' L".parse().unwrap());
+        let mut limits = Limits::new().max_nodes(1000);
+        assert!(matches!(
+            search::parallel_ida_star(c, &h, 20, 2, &mut limits),
+            Err(SearchError::Stopped {
+                reason: StopReason::NodeBudget,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_ida_invalid_cube() {
+        let h = zero_heuristic();
+        // the UB edge flipped in place
+        let c = Cube::from_facelets("UBUUUUUUU RRRRRRRRR FFFFFFFFF DDDDDDDDD LLLLLLLLL BUBBBBBBB")
+            .unwrap();
+
+        assert_eq!(
+            search::ida_star(c, &h, 20, &mut Limits::new()).unwrap_err(),
+            SearchError::InvalidCube(ValidationError::EdgeFlip)
+        );
     }
 }
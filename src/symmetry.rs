@@ -0,0 +1,412 @@
+//! The 48 symmetries of the cube, and pattern database indexes that give symmetric cubes a single
+//! entry.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::cube::{CORNER_FACELETS, EDGE_FACELETS};
+use crate::cubie::CubieCube;
+use crate::pattern::PatternSpec;
+
+lazy_static! {
+    /// Every rotation and reflection of the cube, the identity first.
+    pub static ref SYMMETRIES: Vec<Symmetry> = symmetries();
+}
+
+/// A rotation or reflection of the whole cube.
+///
+/// Conjugating a cube by one, i.e. looking at it rotated or in a mirror and recoloring it so its
+/// centers are where they belong, gives a cube exactly as far from solved, since every move turns
+/// into another move along the way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symmetry {
+    // where each facelet of each corner position goes, as a position and which of its facelets
+    corners: [[(u8, u8); 3]; 8],
+    edges: [[(u8, u8); 2]; 12],
+    /// Whether it's a reflection, which turns clockwise moves counterclockwise.
+    pub mirrored: bool,
+    /// Whether it keeps the U and D faces on the UD axis.
+    pub preserves_ud: bool,
+    /// Position of the symmetry undoing this one in `SYMMETRIES`.
+    pub inverse: usize,
+}
+
+impl Symmetry {
+    /// The cube `c` looks like through this symmetry.
+    pub fn conjugate(&self, c: &CubieCube) -> CubieCube {
+        let mut result = *c;
+
+        // Position facelet (i, k) shows facelet (cp[i], k - co[i]) of the piece there, so the
+        // image of the first facelet of each position shows the image of that piece facelet.
+        for i in 0..8 {
+            let (to, k) = self.corners[i][0];
+            let (piece, l) = self.corners[c.cp[i] as usize][((3 - c.co[i]) % 3) as usize];
+            result.cp[to as usize] = piece;
+            result.co[to as usize] = (k + 3 - l) % 3;
+        }
+
+        for i in 0..12 {
+            let (to, k) = self.edges[i][0];
+            let (piece, l) = self.edges[c.ep[i] as usize][c.eo[i] as usize];
+            result.ep[to as usize] = piece;
+            result.eo[to as usize] = (k + l) % 2;
+        }
+
+        result
+    }
+
+    /// Whether the symmetry maps the pieces `spec` tracks onto themselves.
+    pub fn preserves(&self, spec: &PatternSpec) -> bool {
+        let corners = (0..8).filter(|&x| spec.tracks_corner(x));
+        let edges = (0..12).filter(|&x| spec.tracks_edge(x));
+
+        corners
+            .map(|x| self.corners[x as usize][0].0)
+            .all(|x| spec.tracks_corner(x))
+            && edges
+                .map(|x| self.edges[x as usize][0].0)
+                .all(|x| spec.tracks_edge(x))
+    }
+}
+
+/// One symmetry, by position in `SYMMETRIES`, for each set of pieces other than its own that
+/// those `spec` tracks can be mapped onto. A table looking up a cube's conjugates by these gives
+/// the distances of those other pieces.
+pub fn moving(spec: &PatternSpec) -> Vec<usize> {
+    let mut seen = Vec::new();
+    let mut result = Vec::new();
+    for (s, symmetry) in SYMMETRIES.iter().enumerate() {
+        // the conjugate's tracked pieces are where the inverse takes them from
+        let inverse = &SYMMETRIES[symmetry.inverse];
+        let corners: u8 = (0..8)
+            .filter(|&x| spec.tracks_corner(x))
+            .map(|x| 1 << inverse.corners[x as usize][0].0)
+            .sum();
+        let edges: u16 = (0..12)
+            .filter(|&x| spec.tracks_edge(x))
+            .map(|x| 1 << inverse.edges[x as usize][0].0)
+            .sum();
+
+        if !seen.contains(&(corners, edges)) {
+            seen.push((corners, edges));
+            if s != 0 {
+                result.push(s);
+            }
+        }
+    }
+
+    result
+}
+
+// The symmetries are the 3x3 matrices with one 1 or -1 in each row and column, acting on
+// coordinates with x to the right, y up and z to the front.
+fn symmetries() -> Vec<Symmetry> {
+    let facelets: HashMap<([i8; 3], [i8; 3]), usize> =
+        (0..54).map(|f| (facelet_position(f), f)).collect();
+    let mut corner_facelets = HashMap::new();
+    for (i, position) in CORNER_FACELETS.iter().enumerate() {
+        for (k, &f) in position.iter().enumerate() {
+            corner_facelets.insert(f, (i as u8, k as u8));
+        }
+    }
+    let mut edge_facelets = HashMap::new();
+    for (i, position) in EDGE_FACELETS.iter().enumerate() {
+        for (k, &f) in position.iter().enumerate() {
+            edge_facelets.insert(f, (i as u8, k as u8));
+        }
+    }
+
+    let mut result = Vec::new();
+    for axes in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        for signs in 0..8 {
+            let sign = |a: usize| if signs & (1 << a) == 0 { 1 } else { -1 };
+            let transform = |v: [i8; 3]| [0, 1, 2].map(|a| sign(a) * v[axes[a]]);
+            let image = |f: usize| {
+                let (position, normal) = facelet_position(f);
+                facelets[&(transform(position), transform(normal))]
+            };
+
+            let odd_axes = axes == [0, 2, 1] || axes == [1, 0, 2] || axes == [2, 1, 0];
+            let negative = (0..3).filter(|&a| sign(a) < 0).count() % 2 == 1;
+            result.push(Symmetry {
+                corners: CORNER_FACELETS.map(|p| p.map(|f| corner_facelets[&image(f)])),
+                edges: EDGE_FACELETS.map(|p| p.map(|f| edge_facelets[&image(f)])),
+                mirrored: odd_axes != negative,
+                preserves_ud: axes[1] == 1,
+                inverse: 0,
+            });
+        }
+    }
+
+    for s in 0..result.len() {
+        result[s].inverse = (0..result.len())
+            .find(|&t| {
+                let undone = |i: usize, k: usize| {
+                    let (j, l) = result[s].corners[i][k];
+                    result[t].corners[j as usize][l as usize] == (i as u8, k as u8)
+                };
+                (0..8).all(|i| (0..3).all(|k| undone(i, k)))
+            })
+            .unwrap();
+    }
+
+    result
+}
+
+// Where facelet `f` sits: the cubie it's on, with coordinates from -1 to 1, and the direction it
+// faces. Faces are laid out like the net in `Cube`, U and D with their back and front row first.
+fn facelet_position(f: usize) -> ([i8; 3], [i8; 3]) {
+    let (r, c) = ((f % 9 / 3) as i8, (f % 3) as i8);
+
+    match f / 9 {
+        0 => ([c - 1, 1, r - 1], [0, 1, 0]),
+        1 => ([-1, 1 - r, c - 1], [-1, 0, 0]),
+        2 => ([c - 1, 1 - r, 1], [0, 0, 1]),
+        3 => ([1, 1 - r, 1 - c], [1, 0, 0]),
+        4 => ([1 - c, 1 - r, -1], [0, 0, -1]),
+        _ => ([c - 1, -1, 1 - r], [0, -1, 0]),
+    }
+}
+
+/// Numbers the patterns of a `PatternSpec` up to the symmetries preserving the pieces it tracks,
+/// giving all symmetric patterns one index.
+///
+/// Placements of the tracked pieces are sorted into classes of symmetric ones, each with a
+/// representative. A cube's index is its class together with the orientations of the cube
+/// conjugated to put the representative placement on it, so the table is smaller by about the
+/// number of symmetries.
+///
+/// A few representatives are symmetric to themselves, and then symmetric cubes can have different
+/// indexes depending on which of those symmetries is applied to them. Filling a table has to give
+/// all of them the same distance, see `symmetric_indexes`.
+pub struct SymmetryReduction {
+    spec: PatternSpec,
+    // for each permutation index, its class times 64 plus the symmetry taking it to the
+    // representative
+    classes: Vec<u32>,
+    // permutation index of each class's representative
+    representatives: Vec<u32>,
+    // the symmetries other than the identity that leave each representative where it is, those
+    // of class i from stabilizer_starts[i] to stabilizer_starts[i + 1]
+    stabilizers: Vec<u8>,
+    stabilizer_starts: Vec<u32>,
+}
+
+impl SymmetryReduction {
+    pub fn new(spec: PatternSpec) -> Self {
+        let symmetries: Vec<usize> = (0..SYMMETRIES.len())
+            .filter(|&s| SYMMETRIES[s].preserves(&spec))
+            .collect();
+
+        let unassigned = u32::MAX;
+        let mut classes = vec![unassigned; spec.permutation_size()];
+        let mut representatives = Vec::new();
+        let mut stabilizers = Vec::new();
+        let mut stabilizer_starts = vec![0];
+        for p in 0..classes.len() {
+            if classes[p] != unassigned {
+                continue;
+            }
+
+            let class = representatives.len() as u32;
+            representatives.push(p as u32);
+            let c = spec.from_parts(p, 0);
+            for &s in &symmetries {
+                let q = spec.permutation_index(&SYMMETRIES[s].conjugate(&c));
+                if classes[q] == unassigned {
+                    classes[q] = class * 64 + SYMMETRIES[s].inverse as u32;
+                } else if q == p {
+                    stabilizers.push(s as u8);
+                }
+            }
+            stabilizer_starts.push(stabilizers.len() as u32);
+        }
+
+        SymmetryReduction {
+            spec,
+            classes,
+            representatives,
+            stabilizers,
+            stabilizer_starts,
+        }
+    }
+
+    pub fn spec(&self) -> &PatternSpec {
+        &self.spec
+    }
+
+    /// Number of classes of symmetric placements.
+    pub fn class_count(&self) -> usize {
+        self.representatives.len()
+    }
+
+    /// Number of indexes, i.e. the size of the table.
+    pub fn size(&self) -> usize {
+        self.class_count() * self.spec.orientation_size()
+    }
+
+    pub fn index(&self, c: &CubieCube) -> usize {
+        let entry = self.classes[self.spec.permutation_index(c)];
+        let symmetric = SYMMETRIES[(entry % 64) as usize].conjugate(c);
+
+        (entry / 64) as usize * self.spec.orientation_size()
+            + self.spec.orientation_index(&symmetric)
+    }
+
+    /// Some cube whose `index` is the given one.
+    pub fn from_index(&self, index: usize) -> CubieCube {
+        let class = index / self.spec.orientation_size();
+
+        self.spec.from_parts(
+            self.representatives[class] as usize,
+            index % self.spec.orientation_size(),
+        )
+    }
+
+    /// The other indexes of cubes symmetric to those with the given index, which are as far
+    /// from solved. Most indexes have none.
+    pub fn symmetric_indexes(&self, index: usize) -> Vec<usize> {
+        let class = index / self.spec.orientation_size();
+        let start = self.stabilizer_starts[class] as usize;
+        let end = self.stabilizer_starts[class + 1] as usize;
+        if start == end {
+            return Vec::new();
+        }
+
+        let c = self.from_index(index);
+        let mut result = Vec::new();
+        for &s in &self.stabilizers[start..end] {
+            let symmetric = class * self.spec.orientation_size()
+                + self
+                    .spec
+                    .orientation_index(&SYMMETRIES[s as usize].conjugate(&c));
+            if symmetric != index && !result.contains(&symmetric) {
+                result.push(symmetric);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cubie::CubieCube;
+    use crate::moves::{Algorithm, Move};
+    use crate::pattern::PatternSpec;
+    use crate::symmetry::{moving, SymmetryReduction, SYMMETRIES};
+
+    fn scrambled(alg: &str) -> CubieCube {
+        let mut c = CubieCube::solved();
+        c.twist(&alg.parse::<Algorithm>().unwrap());
+        c
+    }
+
+    #[test]
+    fn test_symmetries() {
+        assert_eq!(SYMMETRIES.len(), 48);
+        assert_eq!(SYMMETRIES.iter().filter(|s| s.mirrored).count(), 24);
+        assert_eq!(SYMMETRIES.iter().filter(|s| s.preserves_ud).count(), 16);
+
+        let c = scrambled("R U2 D' B D' L F2 U' R2 B' D L2 F");
+        assert_eq!(SYMMETRIES[0].conjugate(&c), c);
+
+        let mut conjugates = Vec::new();
+        for s in SYMMETRIES.iter() {
+            let conjugate = s.conjugate(&c);
+            assert_eq!(conjugate.validate(), Ok(()));
+            assert_eq!(SYMMETRIES[s.inverse].conjugate(&conjugate), c);
+            conjugates.push(conjugate);
+
+            // every move looks like some move through the symmetry
+            for &mv in Move::ALL.iter() {
+                let mut moved = c;
+                moved.apply(mv);
+                let image = Move::ALL
+                    .iter()
+                    .find(|&&m| {
+                        let mut solved = CubieCube::solved();
+                        solved.apply(m);
+                        let mut turned = CubieCube::solved();
+                        turned.apply(mv);
+                        s.conjugate(&turned) == solved
+                    })
+                    .unwrap();
+                let mut expected = conjugate;
+                expected.apply(*image);
+                assert_eq!(s.conjugate(&moved), expected);
+            }
+        }
+        conjugates.sort_by_key(|c| (c.cp, c.co, c.ep, c.eo));
+        conjugates.dedup();
+        assert_eq!(conjugates.len(), 48);
+    }
+
+    #[test]
+    fn test_mirror() {
+        // x to -x, which swaps L and R and turns R into L'
+        let mirror = &SYMMETRIES[1];
+        assert!(mirror.mirrored && mirror.preserves_ud);
+        assert_eq!(mirror.conjugate(&scrambled("R")), scrambled("L'"));
+        assert_eq!(
+            mirror.conjugate(&scrambled("F U' R2")),
+            scrambled("F' U L2")
+        );
+    }
+
+    #[test]
+    fn test_reduction() {
+        let spec = PatternSpec::new(0xff, 0).permutation_only();
+        let reduction = SymmetryReduction::new(spec);
+        assert!(reduction.size() * 40 < spec.size(), "{}", reduction.size());
+
+        for i in 0..reduction.size() {
+            assert_eq!(reduction.index(&reduction.from_index(i)), i);
+        }
+
+        // symmetric cubes share an index, or one of its symmetric ones
+        let reduction = SymmetryReduction::new(PatternSpec::new(0x0f, 0));
+        for alg in ["D' F2 L U' B R2 D2 F' U L2", "U R2 F"] {
+            let c = scrambled(alg);
+            let index = reduction.index(&c);
+            let mut indexes = reduction.symmetric_indexes(index);
+            indexes.push(index);
+            for s in SYMMETRIES.iter().filter(|s| s.preserves(reduction.spec())) {
+                assert!(indexes.contains(&reduction.index(&s.conjugate(&c))));
+            }
+        }
+
+        // the edges1 table sees 23 other sets of six edges, among them those of edges2
+        let edges1 = PatternSpec::new(0, 0x03f);
+        let moving = moving(&edges1);
+        assert_eq!(moving.len(), 23);
+        let disturbing = |swaps: &[(usize, usize)]| {
+            let mut c = CubieCube::solved();
+            for &(i, j) in swaps {
+                c.ep.swap(i, j);
+            }
+            c
+        };
+        let (edges1_moved, edges2_moved) = (
+            disturbing(&[(0, 1), (2, 3), (4, 5)]),
+            disturbing(&[(6, 7), (8, 9), (10, 11)]),
+        );
+        let solved = edges1.index(&CubieCube::solved());
+        assert!(moving.iter().any(|&s| {
+            let sees = |c: &CubieCube| edges1.index(&SYMMETRIES[s].conjugate(c)) != solved;
+            sees(&edges2_moved) && !sees(&edges1_moved)
+        }));
+
+        // eight edges in the U and D layers, which the UD preserving symmetries keep there
+        let spec = PatternSpec::new(0, 0xf0f).permutation_only();
+        assert_eq!(SYMMETRIES.iter().filter(|s| s.preserves(&spec)).count(), 16);
+    }
+}
@@ -0,0 +1,185 @@
+//! The pattern database file format, which is used in place through a memory map so that every
+//! solver process shares one copy of a table in the page cache.
+//!
+//! A file is a 64 byte header followed by the packed entries, with numbers little endian:
+//!
+//! | offset | size | field                                                         |
+//! |--------|------|---------------------------------------------------------------|
+//! | 0      | 8    | magic, `CUBEPDB\0`                                            |
+//! | 8      | 4    | format version, currently 1                                   |
+//! | 12     | 4    | packing: 0 for one entry per byte, 1 for nibbles, 2 for mod 3 |
+//! | 16     | 8    | number of entries                                             |
+//! | 24     | 8    | number of bytes of entries after the header                   |
+//! | 32     | 4    | CRC-32 of those bytes                                         |
+//! | 36     | 28   | table identifier like `3x3x3/corners`, padded with zeros      |
+
+use std::io;
+use std::io::Write;
+
+use flate2::Crc;
+
+use crate::packed::Packing;
+
+pub const MAGIC: &[u8; 8] = b"CUBEPDB\0";
+pub const VERSION: u32 = 1;
+pub const HEADER_LEN: usize = 64;
+
+const ID_LEN: usize = 28;
+
+/// What the header of a table file says about it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u32,
+    pub packing: Packing,
+    pub entries: usize,
+    pub data_len: usize,
+    pub checksum: u32,
+    pub id: String,
+}
+
+/// The identifier written for the table called `name`.
+pub fn table_id(name: &str) -> String {
+    format!("3x3x3/{}", name)
+}
+
+pub fn checksum(bytes: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(bytes);
+    crc.sum()
+}
+
+/// Whether `bytes` starts like a table file.
+pub fn is_table_file(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Writes a table file holding `entries` packed entries, already packed into `data`. Fails
+/// without writing anything if `id` is longer than the header has room for.
+pub fn write<W: Write>(
+    mut w: W,
+    id: &str,
+    packing: Packing,
+    entries: usize,
+    data: &[u8],
+) -> io::Result<()> {
+    let id = id.as_bytes();
+    if id.len() > ID_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("table identifier longer than {} bytes", ID_LEN),
+        ));
+    }
+
+    let mut header = [0u8; HEADER_LEN];
+    header[0..8].copy_from_slice(MAGIC);
+    header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&packing_code(packing).to_le_bytes());
+    header[16..24].copy_from_slice(&(entries as u64).to_le_bytes());
+    header[24..32].copy_from_slice(&(data.len() as u64).to_le_bytes());
+    header[32..36].copy_from_slice(&checksum(data).to_le_bytes());
+
+    header[36..36 + id.len()].copy_from_slice(id);
+
+    w.write_all(&header)?;
+    w.write_all(data)?;
+    w.flush()
+}
+
+/// Reads the header at the start of `bytes`, explaining what's wrong if it isn't one this
+/// version understands.
+pub fn read_header(bytes: &[u8]) -> Result<Header, String> {
+    if bytes.len() < HEADER_LEN {
+        return Err(format!("{} bytes is too short for a header", bytes.len()));
+    }
+    if !is_table_file(bytes) {
+        return Err("not a table file".to_string());
+    }
+
+    let u32_at = |i: usize| {
+        let mut b = [0; 4];
+        b.copy_from_slice(&bytes[i..i + 4]);
+        u32::from_le_bytes(b)
+    };
+    let u64_at = |i: usize| {
+        let mut b = [0; 8];
+        b.copy_from_slice(&bytes[i..i + 8]);
+        u64::from_le_bytes(b)
+    };
+
+    let version = u32_at(8);
+    if version != VERSION {
+        return Err(format!("unsupported format version {}", version));
+    }
+    let packing = packing_from_code(u32_at(12)).ok_or(format!("unknown packing {}", u32_at(12)))?;
+
+    let id = &bytes[36..36 + ID_LEN];
+    let id_len = id.iter().position(|&b| b == 0).unwrap_or(ID_LEN);
+
+    Ok(Header {
+        version,
+        packing,
+        entries: u64_at(16) as usize,
+        data_len: u64_at(24) as usize,
+        checksum: u32_at(32),
+        id: String::from_utf8_lossy(&id[..id_len]).into_owned(),
+    })
+}
+
+fn packing_code(packing: Packing) -> u32 {
+    match packing {
+        Packing::Byte => 0,
+        Packing::Nibble => 1,
+        Packing::Mod3 => 2,
+    }
+}
+
+fn packing_from_code(code: u32) -> Option<Packing> {
+    match code {
+        0 => Some(Packing::Byte),
+        1 => Some(Packing::Nibble),
+        2 => Some(Packing::Mod3),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packed::Packing;
+    use crate::table_file::{checksum, read_header, write, Header, HEADER_LEN, VERSION};
+
+    #[test]
+    fn test_header_roundtrip() {
+        let data = [0x21, 0x43, 0x65];
+        let mut file = Vec::new();
+        write(&mut file, "3x3x3/edges1", Packing::Nibble, 6, &data).unwrap();
+        assert_eq!(file.len(), HEADER_LEN + data.len());
+
+        assert_eq!(
+            read_header(&file),
+            Ok(Header {
+                version: VERSION,
+                packing: Packing::Nibble,
+                entries: 6,
+                data_len: 3,
+                checksum: checksum(&data),
+                id: "3x3x3/edges1".to_string(),
+            })
+        );
+
+        file[8] = 2;
+        assert_eq!(
+            read_header(&file),
+            Err("unsupported format version 2".to_string())
+        );
+        assert!(read_header(b"CUBEPDB\0").is_err());
+
+        // ids that would be cut short could be mistaken for each other
+        let mut file = Vec::new();
+        let long = "3x3x3/edges-with-a-long-name-1";
+        assert!(write(&mut file, long, Packing::Byte, 3, &data).is_err());
+        assert!(file.is_empty());
+        let longest = &long[..28];
+        write(&mut file, longest, Packing::Byte, 3, &data).unwrap();
+        assert_eq!(read_header(&file).unwrap().id, longest);
+    }
+}
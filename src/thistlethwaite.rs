@@ -0,0 +1,327 @@
+//! Phases and tables for Thistlethwaite's algorithm, see `search::thistlethwaite`.
+//!
+//! Each phase takes a cube from one subgroup into the next using only the moves of the first,
+//! fewer and fewer moves being allowed along the way:
+//!
+//! - G0 = <U, D, L, R, F, B>, every cube.
+//! - G1 = <U, D, L, R, F2, B2>, where no edge is flipped.
+//! - G2 = <U, D, L2, R2, F2, B2>, where no corner is twisted either and the middle slice edges
+//!   between U and D are in that slice.
+//! - G3 = <U2, D2, L2, R2, F2, B2>, where the other edges are in their slices too and the corners
+//!   are in one of the 96 arrangements half turns reach.
+//! - The solved cube.
+//!
+//! A phase only has to look at the part of the cube that tells its subgroups apart, so each
+//! table holds the exact distance of every value of that part and is small enough to build the
+//! first time it's needed.
+
+use lazy_static::lazy_static;
+
+use crate::cubie::CubieCube;
+use crate::moves::{Face, Move, Turn};
+use crate::rank;
+use crate::rank::BLANK;
+use crate::two_phase::{
+    self, CORNER_PERMUTATIONS, FLIPS, PHASE2_MOVES, SLICES, SLICE_EDGES, TWISTS, UD_EDGES,
+};
+
+const G1_MOVES: [Move; 14] = [
+    Move::new(Face::U, Turn::Clockwise),
+    Move::new(Face::U, Turn::CounterClockwise),
+    Move::new(Face::U, Turn::Half),
+    Move::new(Face::F, Turn::Half),
+    Move::new(Face::R, Turn::Clockwise),
+    Move::new(Face::R, Turn::CounterClockwise),
+    Move::new(Face::R, Turn::Half),
+    Move::new(Face::D, Turn::Clockwise),
+    Move::new(Face::D, Turn::CounterClockwise),
+    Move::new(Face::D, Turn::Half),
+    Move::new(Face::B, Turn::Half),
+    Move::new(Face::L, Turn::Clockwise),
+    Move::new(Face::L, Turn::CounterClockwise),
+    Move::new(Face::L, Turn::Half),
+];
+
+const G3_MOVES: [Move; 6] = [
+    Move::new(Face::U, Turn::Half),
+    Move::new(Face::F, Turn::Half),
+    Move::new(Face::R, Turn::Half),
+    Move::new(Face::D, Turn::Half),
+    Move::new(Face::B, Turn::Half),
+    Move::new(Face::L, Turn::Half),
+];
+
+// edge positions, and pieces, in the slice between L and R and in the one between F and B
+const M_EDGES: [usize; 4] = [0, 3, 8, 11];
+const S_EDGES: [usize; 4] = [1, 2, 9, 10];
+
+// ways to choose where the M slice edges are among the edge positions outside the U-D slice
+const M_SLICES: usize = 70;
+// orders of the edges within their three slices
+const SLICE_ORDERS: usize = 24 * 24 * 24;
+
+/// One step of the reduction, taking cubes from one subgroup into the next.
+pub struct Phase {
+    /// The subgroup the phase ends in.
+    pub target: &'static str,
+    /// The moves of the subgroup it starts in, which are all it uses.
+    pub moves: &'static [Move],
+    distance: fn(&CubieCube) -> u8,
+}
+
+impl Phase {
+    /// The fewest of `moves` that take `c`, which has to be in the subgroup the phase starts in,
+    /// to `target`.
+    pub fn distance(&self, c: &CubieCube) -> u8 {
+        (self.distance)(c)
+    }
+}
+
+/// The four phases, in order.
+pub static PHASES: [Phase; 4] = [
+    Phase {
+        target: "G1 = <U, D, L, R, F2, B2>",
+        moves: &Move::ALL,
+        distance: flip_distance,
+    },
+    Phase {
+        target: "G2 = <U, D, L2, R2, F2, B2>",
+        moves: &G1_MOVES,
+        distance: twist_slice_distance,
+    },
+    Phase {
+        target: "G3 = <U2, D2, L2, R2, F2, B2>",
+        moves: &PHASE2_MOVES,
+        distance: corner_slice_distance,
+    },
+    Phase {
+        target: "solved",
+        moves: &G3_MOVES,
+        distance: half_turn_distance,
+    },
+];
+
+lazy_static! {
+    static ref TABLES: Tables = Tables::new();
+}
+
+struct Tables {
+    // distances by edge flip
+    flip: Vec<u8>,
+    // by corner twist and which positions the U-D slice edges are in
+    twist_slice: Vec<u8>,
+    // by corner permutation and which positions the M slice edges are in
+    corner_slice: Vec<u8>,
+    // by which of the corner permutations in G3 the corners are in and the edge orders
+    half_turns: Vec<u8>,
+    // position of each corner permutation among those in G3, if it is one
+    g3_corners: Vec<u8>,
+}
+
+impl Tables {
+    fn new() -> Self {
+        let flip_moves =
+            two_phase::move_table(FLIPS, &Move::ALL, two_phase::set_flip, two_phase::flip);
+        let flip = two_phase::pruning_table(FLIPS, 1, &[0], Move::ALL.len(), |i, _, m| {
+            (flip_moves[i][m] as usize, 0)
+        });
+
+        let twist_moves =
+            two_phase::move_table(TWISTS, &G1_MOVES, two_phase::set_twist, two_phase::twist);
+        let slice_moves =
+            two_phase::move_table(SLICES, &G1_MOVES, two_phase::set_slice, two_phase::slice);
+        let solved_slice = two_phase::slice(&CubieCube::solved());
+        let twist_slice = two_phase::pruning_table(
+            TWISTS,
+            SLICES,
+            &[solved_slice],
+            G1_MOVES.len(),
+            |i, j, m| (twist_moves[i][m] as usize, slice_moves[j][m] as usize),
+        );
+
+        let corner_moves = two_phase::move_table(
+            CORNER_PERMUTATIONS,
+            &PHASE2_MOVES,
+            two_phase::set_corner_permutation,
+            corner_permutation,
+        );
+        let m_slice_moves = two_phase::move_table(M_SLICES, &PHASE2_MOVES, set_m_slice, m_slice);
+        let g3 = half_turn_corners();
+        let solved_m_slice = m_slice(&CubieCube::solved());
+        let solved: Vec<usize> = g3.iter().map(|&p| p * M_SLICES + solved_m_slice).collect();
+        let corner_slice = two_phase::pruning_table(
+            CORNER_PERMUTATIONS,
+            M_SLICES,
+            &solved,
+            PHASE2_MOVES.len(),
+            |i, j, m| (corner_moves[i][m] as usize, m_slice_moves[j][m] as usize),
+        );
+
+        let mut g3_corners = vec![u8::MAX; CORNER_PERMUTATIONS];
+        for (i, &p) in g3.iter().enumerate() {
+            g3_corners[p] = i as u8;
+        }
+        let g3_corner_moves: Vec<[usize; 6]> = g3
+            .iter()
+            .map(|&p| {
+                let mut row = [0; 6];
+                for (next, &mv) in row.iter_mut().zip(G3_MOVES.iter()) {
+                    let mut c = two_phase::set_corner_permutation(p);
+                    c.apply(mv);
+                    *next = g3_corners[corner_permutation(&c)] as usize;
+                }
+                row
+            })
+            .collect();
+        let order_moves =
+            two_phase::move_table(SLICE_ORDERS, &G3_MOVES, set_slice_orders, slice_orders);
+        let half_turns =
+            two_phase::pruning_table(g3.len(), SLICE_ORDERS, &[0], G3_MOVES.len(), |i, j, m| {
+                (g3_corner_moves[i][m], order_moves[j][m] as usize)
+            });
+
+        Tables {
+            flip,
+            twist_slice,
+            corner_slice,
+            half_turns,
+            g3_corners,
+        }
+    }
+}
+
+fn flip_distance(c: &CubieCube) -> u8 {
+    TABLES.flip[two_phase::flip(c)]
+}
+
+fn twist_slice_distance(c: &CubieCube) -> u8 {
+    TABLES.twist_slice[two_phase::twist(c) * SLICES + two_phase::slice(c)]
+}
+
+fn corner_slice_distance(c: &CubieCube) -> u8 {
+    TABLES.corner_slice[corner_permutation(c) * M_SLICES + m_slice(c)]
+}
+
+fn half_turn_distance(c: &CubieCube) -> u8 {
+    let corners = TABLES.g3_corners[corner_permutation(c)] as usize;
+    TABLES.half_turns[corners * SLICE_ORDERS + slice_orders(c)]
+}
+
+fn corner_permutation(c: &CubieCube) -> usize {
+    rank::rank_permutation(&c.cp)
+}
+
+// the corner permutations half turns reach, solved first
+fn half_turn_corners() -> Vec<usize> {
+    let mut found = vec![corner_permutation(&CubieCube::solved())];
+    let mut i = 0;
+    while i < found.len() {
+        for &mv in G3_MOVES.iter() {
+            let mut c = two_phase::set_corner_permutation(found[i]);
+            c.apply(mv);
+            let p = corner_permutation(&c);
+            if !found.contains(&p) {
+                found.push(p);
+            }
+        }
+        i += 1;
+    }
+
+    found
+}
+
+// which of the positions outside the U-D slice the M slice edges are in
+fn m_slice(c: &CubieCube) -> usize {
+    let mut slots = [BLANK; 8];
+    for (slot, &i) in slots.iter_mut().zip(UD_EDGES.iter()) {
+        if M_EDGES.contains(&(c.ep[i] as usize)) {
+            *slot = 0;
+        }
+    }
+
+    rank::rank_combination(&slots)
+}
+
+fn set_m_slice(s: usize) -> CubieCube {
+    let mut slots = [BLANK; 8];
+    rank::unrank_combination(s, M_EDGES.len(), &mut slots);
+
+    let mut c = CubieCube::solved();
+    let mut m_pieces = M_EDGES.iter();
+    let mut s_pieces = S_EDGES.iter();
+    for (&i, &slot) in UD_EDGES.iter().zip(slots.iter()) {
+        let next = if slot == BLANK {
+            s_pieces.next()
+        } else {
+            m_pieces.next()
+        };
+        c.ep[i] = *next.unwrap() as u8;
+    }
+
+    c
+}
+
+// the order of the edges within each of their slices, for cubes where that's where they are
+fn slice_orders(c: &CubieCube) -> usize {
+    [SLICE_EDGES, M_EDGES, S_EDGES].iter().fold(0, |x, edges| {
+        let mut perm = [0; 4];
+        for (p, &i) in perm.iter_mut().zip(edges.iter()) {
+            let piece = c.ep[i] as usize;
+            *p = edges.iter().position(|&e| e == piece).unwrap_or(0) as u8;
+        }
+        x * 24 + rank::rank_permutation(&perm)
+    })
+}
+
+fn set_slice_orders(mut x: usize) -> CubieCube {
+    let mut c = CubieCube::solved();
+    for edges in [S_EDGES, M_EDGES, SLICE_EDGES].iter() {
+        let mut perm = [0; 4];
+        rank::unrank_permutation(x % 24, &mut perm);
+        x /= 24;
+        for (&i, &p) in edges.iter().zip(perm.iter()) {
+            c.ep[i] = edges[p as usize] as u8;
+        }
+    }
+
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cubie::CubieCube;
+    use crate::moves::Algorithm;
+    use crate::thistlethwaite::{PHASES, TABLES};
+
+    #[test]
+    fn test_table_depths() {
+        // the longest each phase ever takes
+        let deepest = |table: &[u8]| table.iter().filter(|&&d| d != u8::MAX).max().copied();
+        assert_eq!(deepest(&TABLES.flip), Some(7));
+        assert_eq!(deepest(&TABLES.twist_slice), Some(10));
+        assert_eq!(deepest(&TABLES.corner_slice), Some(13));
+        assert_eq!(deepest(&TABLES.half_turns), Some(15));
+    }
+
+    #[test]
+    fn test_subgroups() {
+        // a cube scrambled with the moves a phase starts with is in every earlier target
+        let scrambles = [
+            "R U2 F' L D' B R2 U",
+            "R U2 L' D F2 R' B2 U'",
+            "U R2 D' F2 L2 U' B2 D",
+            "U2 R2 F2 D2 L2 B2 U2 F2",
+        ];
+        for (i, (phase, scramble)) in PHASES.iter().zip(scrambles.iter()).enumerate() {
+            let scramble: Algorithm = scramble.parse().unwrap();
+            assert!(scramble.iter().all(|mv| phase.moves.contains(mv)));
+            let mut c = CubieCube::solved();
+            c.twist(&scramble);
+
+            for earlier in &PHASES[..i] {
+                assert_eq!(earlier.distance(&c), 0, "{}", earlier.target);
+            }
+            assert!(phase.distance(&c) > 0, "{}", phase.target);
+        }
+    }
+}
@@ -0,0 +1,454 @@
+//! Coordinates and tables for Kociemba's two-phase algorithm, see `search::two_phase`.
+//!
+//! Phase 1 brings a cube into the subgroup G1 = <U, D, R2, L2, F2, B2>, where no corner is
+//! twisted, no edge is flipped and the four middle slice edges are in the middle slice. Phase 2
+//! then solves it with those moves alone. Each phase describes a cube by a few coordinates small
+//! enough to tabulate what every move does to them and how many moves are left at least. The
+//! tables are a few megabytes and are built the first time they're needed.
+
+use std::collections::VecDeque;
+
+use lazy_static::lazy_static;
+
+use crate::cubie::CubieCube;
+use crate::moves::{Face, Move, Turn};
+use crate::rank;
+use crate::rank::BLANK;
+
+pub(crate) const TWISTS: usize = 2187;
+pub(crate) const FLIPS: usize = 2048;
+pub(crate) const SLICES: usize = 495;
+pub(crate) const CORNER_PERMUTATIONS: usize = 40320;
+const EDGE_PERMUTATIONS: usize = 40320;
+const SLICE_PERMUTATIONS: usize = 24;
+
+// edge positions, and pieces, in the middle slice and in the U and D layers
+pub(crate) const SLICE_EDGES: [usize; 4] = [4, 5, 6, 7];
+pub(crate) const UD_EDGES: [usize; 8] = [0, 1, 2, 3, 8, 9, 10, 11];
+
+/// The moves generating G1, in the order of `Move::ALL`.
+pub const PHASE2_MOVES: [Move; 10] = [
+    Move::new(Face::U, Turn::Clockwise),
+    Move::new(Face::U, Turn::CounterClockwise),
+    Move::new(Face::U, Turn::Half),
+    Move::new(Face::F, Turn::Half),
+    Move::new(Face::R, Turn::Half),
+    Move::new(Face::D, Turn::Clockwise),
+    Move::new(Face::D, Turn::CounterClockwise),
+    Move::new(Face::D, Turn::Half),
+    Move::new(Face::B, Turn::Half),
+    Move::new(Face::L, Turn::Half),
+];
+
+lazy_static! {
+    static ref TABLES: Tables = Tables::new();
+}
+
+/// Whether `mv` keeps cubes in G1.
+pub fn is_phase2_move(mv: Move) -> bool {
+    PHASE2_MOVES.contains(&mv)
+}
+
+/// A cube as far as phase 1 is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Phase1 {
+    twist: u16,
+    flip: u16,
+    slice: u16,
+}
+
+impl Phase1 {
+    pub fn new(c: &CubieCube) -> Self {
+        Phase1 {
+            twist: twist(c) as u16,
+            flip: flip(c) as u16,
+            slice: slice(c) as u16,
+        }
+    }
+
+    pub fn apply(self, mv: Move) -> Self {
+        let t = &*TABLES;
+        let m = mv.index();
+
+        Phase1 {
+            twist: t.twist_moves[self.twist as usize][m],
+            flip: t.flip_moves[self.flip as usize][m],
+            slice: t.slice_moves[self.slice as usize][m],
+        }
+    }
+
+    /// Whether the cube is in G1.
+    pub fn is_solved(self) -> bool {
+        self.twist == 0 && self.flip == 0 && self.slice == TABLES.solved_slice
+    }
+
+    /// A lower bound on the moves it takes to reach G1.
+    pub fn h(self) -> u8 {
+        let t = &*TABLES;
+        let slice = self.slice as usize;
+
+        t.twist_slice[self.twist as usize * SLICES + slice]
+            .max(t.flip_slice[self.flip as usize * SLICES + slice])
+    }
+}
+
+/// A cube in G1 as far as phase 2 is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Phase2 {
+    corners: u16,
+    edges: u16,
+    slice: u8,
+}
+
+impl Phase2 {
+    /// Takes `c`'s coordinates, which only mean something if it's in G1.
+    pub fn new(c: &CubieCube) -> Self {
+        Phase2 {
+            corners: rank::rank_permutation(&c.cp) as u16,
+            edges: edge_permutation(c) as u16,
+            slice: slice_permutation(c) as u8,
+        }
+    }
+
+    /// Applies the move at `m` in `PHASE2_MOVES`.
+    pub fn apply(self, m: usize) -> Self {
+        let t = &*TABLES;
+
+        Phase2 {
+            corners: t.corner_moves[self.corners as usize][m],
+            edges: t.edge_moves[self.edges as usize][m],
+            slice: t.slice_permutation_moves[self.slice as usize][m],
+        }
+    }
+
+    pub fn is_solved(self) -> bool {
+        self.corners == 0 && self.edges == 0 && self.slice == 0
+    }
+
+    /// A lower bound on the moves of G1 it takes to solve the cube.
+    pub fn h(self) -> u8 {
+        let t = &*TABLES;
+        let slice = self.slice as usize;
+
+        t.corner_slice[self.corners as usize * SLICE_PERMUTATIONS + slice]
+            .max(t.edge_slice[self.edges as usize * SLICE_PERMUTATIONS + slice])
+    }
+}
+
+struct Tables {
+    twist_moves: Vec<[u16; 18]>,
+    flip_moves: Vec<[u16; 18]>,
+    slice_moves: Vec<[u16; 18]>,
+    corner_moves: Vec<[u16; 10]>,
+    edge_moves: Vec<[u16; 10]>,
+    slice_permutation_moves: Vec<[u8; 10]>,
+    solved_slice: u16,
+    // distances to G1 by twist and slice, and by flip and slice
+    twist_slice: Vec<u8>,
+    flip_slice: Vec<u8>,
+    // distances to solved within G1 by corner permutation and slice permutation, and by edge
+    // permutation and slice permutation
+    corner_slice: Vec<u8>,
+    edge_slice: Vec<u8>,
+}
+
+impl Tables {
+    fn new() -> Self {
+        let twist_moves = move_table(TWISTS, &Move::ALL, set_twist, twist);
+        let flip_moves = move_table(FLIPS, &Move::ALL, set_flip, flip);
+        let slice_moves = move_table(SLICES, &Move::ALL, set_slice, slice);
+        let corner_moves = move_table(
+            CORNER_PERMUTATIONS,
+            &PHASE2_MOVES,
+            set_corner_permutation,
+            |c| rank::rank_permutation(&c.cp),
+        );
+        let edge_moves = move_table(
+            EDGE_PERMUTATIONS,
+            &PHASE2_MOVES,
+            set_edge_permutation,
+            edge_permutation,
+        );
+        let slice_permutation_moves: Vec<[u8; 10]> = move_table(
+            SLICE_PERMUTATIONS,
+            &PHASE2_MOVES,
+            set_slice_permutation,
+            slice_permutation,
+        )
+        .iter()
+        .map(|row| {
+            let mut narrow = [0; 10];
+            for (n, &x) in narrow.iter_mut().zip(row.iter()) {
+                *n = x as u8;
+            }
+            narrow
+        })
+        .collect();
+
+        let solved_slice = slice(&CubieCube::solved());
+        let twist_slice = pruning_table(TWISTS, SLICES, &[solved_slice], 18, |i, j, m| {
+            (twist_moves[i][m] as usize, slice_moves[j][m] as usize)
+        });
+        let flip_slice = pruning_table(FLIPS, SLICES, &[solved_slice], 18, |i, j, m| {
+            (flip_moves[i][m] as usize, slice_moves[j][m] as usize)
+        });
+        let corner_slice = pruning_table(
+            CORNER_PERMUTATIONS,
+            SLICE_PERMUTATIONS,
+            &[0],
+            10,
+            |i, j, m| {
+                (
+                    corner_moves[i][m] as usize,
+                    slice_permutation_moves[j][m] as usize,
+                )
+            },
+        );
+        let edge_slice = pruning_table(
+            EDGE_PERMUTATIONS,
+            SLICE_PERMUTATIONS,
+            &[0],
+            10,
+            |i, j, m| {
+                (
+                    edge_moves[i][m] as usize,
+                    slice_permutation_moves[j][m] as usize,
+                )
+            },
+        );
+
+        Tables {
+            twist_moves,
+            flip_moves,
+            slice_moves,
+            corner_moves,
+            edge_moves,
+            slice_permutation_moves,
+            solved_slice: solved_slice as u16,
+            twist_slice,
+            flip_slice,
+            corner_slice,
+            edge_slice,
+        }
+    }
+}
+
+// the coordinate of each coordinate value after each move, by way of a cube having that value
+pub(crate) fn move_table<const N: usize>(
+    size: usize,
+    moves: &[Move; N],
+    set: fn(usize) -> CubieCube,
+    get: fn(&CubieCube) -> usize,
+) -> Vec<[u16; N]> {
+    (0..size)
+        .map(|x| {
+            let c = set(x);
+            let mut row = [0; N];
+            for (next, &mv) in row.iter_mut().zip(moves.iter()) {
+                let mut c = c;
+                c.apply(mv);
+                *next = get(&c) as u16;
+            }
+            row
+        })
+        .collect()
+}
+
+// distances from the nearest of `solved` over pairs of coordinates, indexed by
+// first * second_size + second, found breadth first with `next` giving the pair after each of
+// `moves` moves
+pub(crate) fn pruning_table<F: Fn(usize, usize, usize) -> (usize, usize)>(
+    first_size: usize,
+    second_size: usize,
+    solved: &[usize],
+    moves: usize,
+    next: F,
+) -> Vec<u8> {
+    let mut table = vec![u8::MAX; first_size * second_size];
+    let mut queue = VecDeque::new();
+    for &index in solved {
+        table[index] = 0;
+        queue.push_back(index);
+    }
+
+    while let Some(index) = queue.pop_front() {
+        let (i, j) = (index / second_size, index % second_size);
+        for m in 0..moves {
+            let (a, b) = next(i, j, m);
+            let neighbor = a * second_size + b;
+            if table[neighbor] == u8::MAX {
+                table[neighbor] = table[index] + 1;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    table
+}
+
+// corner twists in base 3, leaving out the last, which the others determine
+pub(crate) fn twist(c: &CubieCube) -> usize {
+    c.co[..7].iter().fold(0, |t, &x| t * 3 + x as usize)
+}
+
+pub(crate) fn set_twist(mut t: usize) -> CubieCube {
+    let mut c = CubieCube::solved();
+    for i in (0..7).rev() {
+        c.co[i] = (t % 3) as u8;
+        t /= 3;
+    }
+    c.co[7] = (3 - c.co[..7].iter().sum::<u8>() % 3) % 3;
+
+    c
+}
+
+// edge flips in base 2, leaving out the last likewise
+pub(crate) fn flip(c: &CubieCube) -> usize {
+    c.eo[..11].iter().fold(0, |f, &x| f * 2 + x as usize)
+}
+
+pub(crate) fn set_flip(mut f: usize) -> CubieCube {
+    let mut c = CubieCube::solved();
+    for i in (0..11).rev() {
+        c.eo[i] = (f % 2) as u8;
+        f /= 2;
+    }
+    c.eo[11] = c.eo[..11].iter().sum::<u8>() % 2;
+
+    c
+}
+
+fn is_slice_edge(piece: u8) -> bool {
+    SLICE_EDGES.contains(&(piece as usize))
+}
+
+// which four positions the middle slice edges are in, whatever their order
+pub(crate) fn slice(c: &CubieCube) -> usize {
+    let mut slots = [BLANK; 12];
+    for (slot, &piece) in slots.iter_mut().zip(c.ep.iter()) {
+        if is_slice_edge(piece) {
+            *slot = 0;
+        }
+    }
+
+    rank::rank_combination(&slots)
+}
+
+pub(crate) fn set_slice(s: usize) -> CubieCube {
+    let mut slots = [BLANK; 12];
+    rank::unrank_combination(s, SLICE_EDGES.len(), &mut slots);
+
+    let mut c = CubieCube::solved();
+    let mut slice_pieces = SLICE_EDGES.iter();
+    let mut other_pieces = UD_EDGES.iter();
+    for (piece, &slot) in c.ep.iter_mut().zip(slots.iter()) {
+        let next = if slot == BLANK {
+            other_pieces.next()
+        } else {
+            slice_pieces.next()
+        };
+        *piece = *next.unwrap() as u8;
+    }
+
+    c
+}
+
+pub(crate) fn set_corner_permutation(p: usize) -> CubieCube {
+    let mut c = CubieCube::solved();
+    rank::unrank_permutation(p, &mut c.cp);
+
+    c
+}
+
+// the order of the U and D layer edges, for cubes where that's where they are
+fn edge_permutation(c: &CubieCube) -> usize {
+    let mut perm = [0; 8];
+    for (p, &i) in perm.iter_mut().zip(UD_EDGES.iter()) {
+        let piece = c.ep[i] as usize;
+        *p = UD_EDGES.iter().position(|&e| e == piece).unwrap_or(0) as u8;
+    }
+
+    rank::rank_permutation(&perm)
+}
+
+fn set_edge_permutation(p: usize) -> CubieCube {
+    let mut perm = [0; 8];
+    rank::unrank_permutation(p, &mut perm);
+
+    let mut c = CubieCube::solved();
+    for (&i, &x) in UD_EDGES.iter().zip(perm.iter()) {
+        c.ep[i] = UD_EDGES[x as usize] as u8;
+    }
+
+    c
+}
+
+// the order of the middle slice edges, for cubes where that's where they are
+fn slice_permutation(c: &CubieCube) -> usize {
+    let mut perm = [0; 4];
+    for (p, &i) in perm.iter_mut().zip(SLICE_EDGES.iter()) {
+        *p = c.ep[i].wrapping_sub(SLICE_EDGES[0] as u8).min(3);
+    }
+
+    rank::rank_permutation(&perm)
+}
+
+fn set_slice_permutation(p: usize) -> CubieCube {
+    let mut perm = [0; 4];
+    rank::unrank_permutation(p, &mut perm);
+
+    let mut c = CubieCube::solved();
+    for (&i, &x) in SLICE_EDGES.iter().zip(perm.iter()) {
+        c.ep[i] = SLICE_EDGES[x as usize] as u8;
+    }
+
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cubie::CubieCube;
+    use crate::moves::{Algorithm, Move};
+    use crate::two_phase::{is_phase2_move, Phase1, Phase2, PHASE2_MOVES};
+
+    #[test]
+    fn test_g1() {
+        // exactly the moves of G1 keep a solved cube there
+        for &mv in Move::ALL.iter() {
+            let mut c = CubieCube::solved();
+            c.apply(mv);
+            assert_eq!(Phase1::new(&c).is_solved(), is_phase2_move(mv), "{}", mv);
+        }
+
+        let alg: Algorithm = "U R2 F2 D' L2 B2 U2 R2 D".parse().unwrap();
+        let mut c = CubieCube::solved();
+        c.twist(&alg);
+        assert!(Phase1::new(&c).is_solved());
+        assert!(!Phase2::new(&c).is_solved());
+        assert!(Phase2::new(&c).h() <= alg.len() as u8);
+    }
+
+    #[test]
+    fn test_coordinates_follow_moves() {
+        let alg: Algorithm = "U F' R2 U2 R B' R2 B R U L2 R2 F' L R2 F L' R F' B2 R B L' R' B"
+            .parse()
+            .unwrap();
+        let mut c = CubieCube::solved();
+        let mut phase1 = Phase1::new(&c);
+        for &mv in &alg {
+            c.apply(mv);
+            phase1 = phase1.apply(mv);
+            assert_eq!(phase1, Phase1::new(&c));
+        }
+        assert!(!phase1.is_solved());
+        assert!(phase1.h() > 0);
+
+        let mut c = CubieCube::solved();
+        let mut phase2 = Phase2::new(&c);
+        for m in [0, 4, 8, 5, 3, 9, 2, 4, 7, 1] {
+            c.apply(PHASE2_MOVES[m]);
+            phase2 = phase2.apply(m);
+            assert_eq!(phase2, Phase2::new(&c));
+        }
+    }
+}